@@ -0,0 +1,28 @@
+use std::fmt;
+
+///
+///RTree errors
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    ///Query on an index without items
+    EmptyIndex,
+    ///Envelope or point with a NaN or infinite coordinate
+    NonFiniteCoordinate,
+    ///NaN distance or radius
+    InvalidDistance,
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::EmptyIndex => write!(f, "empty index"),
+            Error::NonFiniteCoordinate => write!(f, "non-finite coordinate"),
+            Error::InvalidDistance => write!(f, "invalid distance"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
@@ -0,0 +1,36 @@
+use rstar::{RTreeObject, Envelope};
+use crate::{RTree, KObj, FarObj, KnnIter, Scalar};
+use crate::geom;
+
+impl<T> RTree<T> where T: RTreeObject + Clone {
+    ///Lazy farthest-first iterator in order of decreasing `fn_dist_score`,
+    ///by best-first traversal of a max-heap. The score of a parent node must
+    ///be an upper bound of the scores of its items (e.g. the distance to the
+    ///farthest corner of `KObj.mbr`).
+    pub fn farthest_iter<'a, 'q, Q, F>(&'a self, query: &'q Q, fn_dist_score: F)
+                                       -> KnnIter<'a, 'q, T, Q, F, fn(KObj<T::Envelope>) -> bool, FarObj<T::Envelope>>
+        where Q: ?Sized,
+              F: Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T> {
+        KnnIter::new(self, query, fn_dist_score, Self::knn_no_prune as fn(KObj<T::Envelope>) -> bool)
+    }
+
+    ///k farthest items from query in order of decreasing `fn_dist_score`;
+    ///a `limit` of 0 is unbounded, see `farthest_iter`
+    pub fn farthest<Q>(&self, query: &Q, limit: usize,
+                       fn_dist_score: impl Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>) -> Vec<(&T, Scalar<T>)>
+        where Q: ?Sized {
+        let iter = self.farthest_iter(query, fn_dist_score);
+        if limit == 0 {
+            iter.collect()
+        } else {
+            iter.take(limit).collect()
+        }
+    }
+
+    ///k farthest items from point by squared distance to the farthest corner
+    ///of their envelope, in order of decreasing distance
+    pub fn farthest_from_point(&self, pt: &<T::Envelope as Envelope>::Point,
+                               limit: usize) -> Vec<(&T, Scalar<T>)> {
+        self.farthest(pt, limit, |q, _, o| geom::envelope_max_distance_2(&o.mbr, q))
+    }
+}
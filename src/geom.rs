@@ -0,0 +1,477 @@
+use rstar::{Envelope, Point};
+use math_util::EPSILON;
+use math_util::num::{Zero, One, Bounded, Float, NumCast};
+
+///Largest separation between envelopes along an axis, zero if they intersect.
+///A lower bound of the euclidean distance between envelopes.
+pub fn envelope_gap<E>(a: &E, b: &E) -> <E::Point as Point>::Scalar where E: Envelope {
+    let (a_ll, a_ur) = (a.lower_left(), a.upper_right());
+    let (b_ll, b_ur) = (b.lower_left(), b.upper_right());
+    let mut gap = Zero::zero();
+    for i in 0..<E::Point as Point>::DIMENSIONS {
+        let d = if a_ur.nth(i) < b_ll.nth(i) {
+            b_ll.nth(i) - a_ur.nth(i)
+        } else if b_ur.nth(i) < a_ll.nth(i) {
+            a_ll.nth(i) - b_ur.nth(i)
+        } else {
+            continue;
+        };
+        if d > gap {
+            gap = d;
+        }
+    }
+    gap
+}
+
+///Squared euclidean distance between envelopes, zero if they intersect
+pub fn envelope_distance_2<E>(a: &E, b: &E) -> <E::Point as Point>::Scalar where E: Envelope {
+    let (a_ll, a_ur) = (a.lower_left(), a.upper_right());
+    let (b_ll, b_ur) = (b.lower_left(), b.upper_right());
+    let mut dist = Zero::zero();
+    for i in 0..<E::Point as Point>::DIMENSIONS {
+        let d = if a_ur.nth(i) < b_ll.nth(i) {
+            b_ll.nth(i) - a_ur.nth(i)
+        } else if b_ur.nth(i) < a_ll.nth(i) {
+            a_ll.nth(i) - b_ur.nth(i)
+        } else {
+            continue;
+        };
+        dist = dist + d * d;
+    }
+    dist
+}
+
+///Euclidean distance between envelopes is less than `dist`; falls back to
+///their gap when `dist * dist` is not representable in the scalar type
+pub fn envelope_within_distance<E>(a: &E, b: &E, dist: <E::Point as Point>::Scalar) -> bool where E: Envelope {
+    if envelope_gap(a, b) >= dist {
+        return false;
+    }
+    square_overflows::<E::Point>(dist) || envelope_distance_2(a, b) < dist * dist
+}
+
+///Euclidean distance between envelopes is at most `dist`, see `envelope_within_distance`
+pub fn envelope_within_distance_inclusive<E>(a: &E, b: &E, dist: <E::Point as Point>::Scalar) -> bool where E: Envelope {
+    if envelope_gap(a, b) > dist {
+        return false;
+    }
+    square_overflows::<E::Point>(dist) || envelope_distance_2(a, b) <= dist * dist
+}
+
+///`dist * dist` is not representable in the scalar type
+#[inline]
+fn square_overflows<P>(dist: P::Scalar) -> bool where P: Point {
+    dist > Zero::zero() && dist > P::Scalar::max_value() / dist
+}
+
+///Squared euclidean distance from point to the farthest corner of envelope
+pub fn envelope_max_distance_2<E>(envelope: &E, pt: &E::Point) -> <E::Point as Point>::Scalar where E: Envelope {
+    let (ll, ur) = (envelope.lower_left(), envelope.upper_right());
+    let mut dist = Zero::zero();
+    for i in 0..<E::Point as Point>::DIMENSIONS {
+        let (lo, hi) = (pt.nth(i) - ll.nth(i), ur.nth(i) - pt.nth(i));
+        let d = if lo > hi { lo } else { hi };
+        dist = dist + d * d;
+    }
+    dist
+}
+
+///Point coordinates are neither NaN nor infinite
+pub fn is_finite_point<P>(pt: &P) -> bool where P: Point {
+    let (lo, hi) = (P::Scalar::min_value(), P::Scalar::max_value());
+    (0..P::DIMENSIONS).all(|i| (lo..=hi).contains(&pt.nth(i)))
+}
+
+///Scalar is NaN
+#[inline]
+pub fn is_nan<S>(v: S) -> bool where S: PartialOrd {
+    v.partial_cmp(&v).is_none()
+}
+
+///Envelope coordinates are neither NaN nor infinite
+pub fn is_finite<E>(envelope: &E) -> bool where E: Envelope {
+    is_finite_point(&envelope.lower_left()) && is_finite_point(&envelope.upper_right())
+}
+
+///Relation of an envelope to a polygon
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Relation {
+    Disjoint,
+    Within,
+    Intersects,
+}
+
+///Point from x and y
+#[inline]
+pub fn point_xy<P>(x: P::Scalar, y: P::Scalar) -> P where P: Point {
+    P::generate(|i| if i == 0 { x } else { y })
+}
+
+///Cross product of (b - a) and (c - a), positive if c is left of ab
+#[inline]
+pub fn orient<P>(a: &P, b: &P, c: &P) -> P::Scalar where P: Point {
+    (b.nth(0) - a.nth(0)) * (c.nth(1) - a.nth(1)) -
+        (b.nth(1) - a.nth(1)) * (c.nth(0) - a.nth(0))
+}
+
+///Collinear point c lies within the bounds of segment ab
+#[inline]
+fn in_bounds<P>(a: &P, b: &P, c: &P) -> bool where P: Point {
+    (0..2).all(|i| {
+        let (lo, hi) = if a.nth(i) < b.nth(i) { (a.nth(i), b.nth(i)) } else { (b.nth(i), a.nth(i)) };
+        (lo..=hi).contains(&c.nth(i))
+    })
+}
+
+///Segments ab and cd intersect, including touching end points
+pub fn segments_intersect<P>(a: &P, b: &P, c: &P, d: &P) -> bool where P: Point {
+    let zero = P::Scalar::zero();
+    let (d1, d2) = (orient(c, d, a), orient(c, d, b));
+    let (d3, d4) = (orient(a, b, c), orient(a, b, d));
+
+    if ((d1 > zero && d2 < zero) || (d1 < zero && d2 > zero)) &&
+        ((d3 > zero && d4 < zero) || (d3 < zero && d4 > zero)) {
+        return true;
+    }
+    (d1 == zero && in_bounds(c, d, a)) ||
+        (d2 == zero && in_bounds(c, d, b)) ||
+        (d3 == zero && in_bounds(a, b, c)) ||
+        (d4 == zero && in_bounds(a, b, d))
+}
+
+///Edges of a ring, the last vertex connects to the first
+#[inline]
+pub fn ring_edges<P>(ring: &[P]) -> impl Iterator<Item=(&P, &P)> {
+    ring.iter().zip(ring.iter().cycle().skip(1))
+}
+
+///Corners of envelope in counter clockwise order from lower left
+pub fn envelope_corners<E>(envelope: &E) -> [E::Point; 4] where E: Envelope {
+    let (ll, ur) = (envelope.lower_left(), envelope.upper_right());
+    [
+        ll,
+        point_xy(ur.nth(0), ll.nth(1)),
+        ur,
+        point_xy(ll.nth(0), ur.nth(1)),
+    ]
+}
+
+///Segment ab intersects envelope
+pub fn segment_intersects_envelope<E>(a: &E::Point, b: &E::Point, envelope: &E) -> bool where E: Envelope {
+    if envelope.contains_point(a) || envelope.contains_point(b) {
+        return true;
+    }
+    let corners = envelope_corners(envelope);
+    ring_edges(&corners).any(|(c, d)| segments_intersect(a, b, c, d))
+}
+
+///Point in polygon (open or closed ring) by winding number, boundary points are inside
+pub fn point_in_polygon<P>(pt: &P, polygon: &[P]) -> bool where P: Point {
+    let zero = P::Scalar::zero();
+    let mut wn = 0i32;
+    for (a, b) in ring_edges(polygon) {
+        let side = orient(a, b, pt);
+        if side == zero && in_bounds(a, b, pt) {
+            return true;
+        }
+        if a.nth(1) <= pt.nth(1) {
+            if b.nth(1) > pt.nth(1) && side > zero {
+                wn += 1;
+            }
+        } else if b.nth(1) <= pt.nth(1) && side < zero {
+            wn -= 1;
+        }
+    }
+    wn != 0
+}
+
+///Relation of envelope to polygon (open or closed ring)
+pub fn envelope_polygon_relation<E>(envelope: &E, polygon: &[E::Point]) -> Relation where E: Envelope {
+    if polygon.is_empty() {
+        return Relation::Disjoint;
+    }
+    if ring_edges(polygon).any(|(a, b)| segment_intersects_envelope(a, b, envelope)) {
+        return Relation::Intersects;
+    }
+    //boundaries are disjoint: envelope inside polygon, polygon inside envelope or apart
+    if point_in_polygon(&envelope.lower_left(), polygon) {
+        Relation::Within
+    } else if envelope.contains_point(&polygon[0]) {
+        Relation::Intersects
+    } else {
+        Relation::Disjoint
+    }
+}
+
+///Segments of a polyline, a single vertex is a zero-length segment
+#[inline]
+pub fn polyline_segments<P>(line: &[P]) -> impl Iterator<Item=(&P, &P)> {
+    let vertex = if line.len() == 1 { line.first() } else { None };
+    line.windows(2).map(|w| (&w[0], &w[1])).chain(vertex.map(|p| (p, p)))
+}
+
+#[inline]
+fn epsilon<S>() -> S where S: Float {
+    <S as NumCast>::from(EPSILON).unwrap_or_else(S::epsilon)
+}
+
+#[inline]
+fn feq<S>(a: S, b: S) -> bool where S: Float {
+    Float::abs(a - b) < epsilon()
+}
+
+#[inline]
+fn pt_equals<P>(a: &P, b: &P) -> bool where P: Point, P::Scalar: Float {
+    feq(a.nth(0), b.nth(0)) && feq(a.nth(1), b.nth(1))
+}
+
+///Snap value to zero or one
+#[inline]
+pub fn snap_to_zero_or_one<S>(x: S) -> S where S: Float {
+    if feq(x, S::zero()) { S::zero() } else if feq(x, S::one()) { S::one() } else { x }
+}
+
+///Distance between two segments
+pub fn seg_seg_distance<P>(sa: P, sb: P, oa: P, ob: P) -> P::Scalar where P: Point, P::Scalar: Float {
+    let (zero, one) = (P::Scalar::zero(), P::Scalar::one());
+    let (x1, y1) = (sa.nth(0), sa.nth(1));
+    let (x2, y2) = (sb.nth(0), sb.nth(1));
+    let (x3, y3) = (oa.nth(0), oa.nth(1));
+    let (x4, y4) = (ob.nth(0), ob.nth(1));
+
+    let denom = (y4 - y3) * (x2 - x1) - (x4 - x3) * (y2 - y1);
+    let numera = (x4 - x3) * (y1 - y3) - (y4 - y3) * (x1 - x3);
+    let numerb = (x2 - x1) * (y1 - y3) - (y2 - y1) * (x1 - x3);
+
+    if Float::abs(denom) < epsilon() {
+        let is_aspt_a = pt_equals(&sa, &sb);
+        let is_aspt_b = pt_equals(&oa, &ob);
+
+        if is_aspt_a && is_aspt_b {
+            (x1 - x4).hypot(y1 - y4)
+        } else if is_aspt_a {
+            distance_to_point(oa, ob, sa)
+        } else if is_aspt_b {
+            distance_to_point(sa, sb, oa)
+        } else {
+            min_dist_segment_endpoints(sa, sb, oa, ob)
+        }
+    } else {
+        let mua = snap_to_zero_or_one(numera / denom);
+        let mub = snap_to_zero_or_one(numerb / denom);
+
+        //closest end points if intersection is not along the segments
+        let pta = if mua < zero { Some(sa) } else if mua > one { Some(sb) } else { None };
+        let ptb = if mub < zero { Some(oa) } else if mub > one { Some(ob) } else { None };
+
+        match (pta, ptb) {
+            (Some(pta), Some(ptb)) => Float::min(
+                distance_to_point(oa, ob, pta),
+                distance_to_point(sa, sb, ptb),
+            ),
+            (Some(pta), None) => distance_to_point(oa, ob, pta),
+            (None, Some(ptb)) => distance_to_point(sa, sb, ptb),
+            (None, None) => zero, //segments intersect
+        }
+    }
+}
+
+fn min_dist_segment_endpoints<P>(sa: P, sb: P, oa: P, ob: P) -> P::Scalar where P: Point, P::Scalar: Float {
+    let o_sa = distance_to_point(oa, ob, sa);
+    let o_sb = distance_to_point(oa, ob, sb);
+    let s_oa = distance_to_point(sa, sb, oa);
+    let s_ob = distance_to_point(sa, sb, ob);
+    Float::min(Float::min(o_sa, o_sb), Float::min(s_oa, s_ob))
+}
+
+///Distance from segment to point
+pub fn distance_to_point<P>(sa: P, sb: P, pt: P) -> P::Scalar where P: Point, P::Scalar: Float {
+    let (zero, one) = (P::Scalar::zero(), P::Scalar::one());
+    let (ax, ay) = (sa.nth(0), sa.nth(1));
+    let (bx, by) = (sb.nth(0), sb.nth(1));
+    let (px, py) = (pt.nth(0), pt.nth(1));
+    let (dx, dy) = (bx - ax, by - ay);
+
+    if feq(dx, zero) && feq(dy, zero) {
+        //segment with zero length
+        (px - ax).hypot(py - ay)
+    } else {
+        let u = (((px - ax) * dx) + ((py - ay) * dy)) / (dx * dx + dy * dy);
+
+        let (c_ptx, c_pty) = if u < zero {
+            (ax, ay)
+        } else if u > one {
+            (bx, by)
+        } else {
+            (ax + u * dx, ay + u * dy)
+        };
+        (px - c_ptx).hypot(py - c_pty)
+    }
+}
+
+///Distance from segment to envelope, zero if they intersect
+pub fn segment_envelope_distance<E>(a: &E::Point, b: &E::Point, envelope: &E)
+                                    -> <E::Point as Point>::Scalar
+    where E: Envelope, <E::Point as Point>::Scalar: Float {
+    if segment_intersects_envelope(a, b, envelope) {
+        return Zero::zero();
+    }
+    let corners = envelope_corners(envelope);
+    ring_edges(&corners)
+        .map(|(c, d)| seg_seg_distance(*a, *b, *c, *d))
+        .fold(Float::infinity(), Float::min)
+}
+
+///Entry parameter t in [0, max_t] of ray origin + t * direction into envelope,
+///None if the ray misses the envelope
+pub fn ray_envelope_entry<E>(origin: &E::Point, direction: &E::Point, envelope: &E,
+                             max_t: <E::Point as Point>::Scalar) -> Option<<E::Point as Point>::Scalar>
+    where E: Envelope, <E::Point as Point>::Scalar: Float {
+    let (ll, ur) = (envelope.lower_left(), envelope.upper_right());
+    let (mut t0, mut t1) = (Zero::zero(), max_t);
+    for i in 0..<E::Point as Point>::DIMENSIONS {
+        let (o, d) = (origin.nth(i), direction.nth(i));
+        let (lo, hi) = (ll.nth(i), ur.nth(i));
+        if d == Zero::zero() {
+            if o < lo || o > hi {
+                return None;
+            }
+            continue;
+        }
+        let (mut ta, mut tb) = ((lo - o) / d, (hi - o) / d);
+        if ta > tb {
+            std::mem::swap(&mut ta, &mut tb);
+        }
+        t0 = Float::max(t0, ta);
+        t1 = Float::min(t1, tb);
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some(t0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rstar::AABB;
+
+    fn square() -> Vec<[f64; 2]> {
+        vec![[0., 0.], [4., 0.], [4., 4.], [0., 4.]]
+    }
+
+    ///U-shaped ring with a notch over x in [2, 4], y in [2, 6]
+    fn concave() -> Vec<[f64; 2]> {
+        vec![[0., 0.], [6., 0.], [6., 6.], [4., 6.], [4., 2.], [2., 2.], [2., 6.], [0., 6.]]
+    }
+
+    #[test]
+    fn test_segments_intersect() {
+        //proper crossing
+        assert!(segments_intersect(&[0., 0.], &[2., 2.], &[0., 2.], &[2., 0.]));
+        //collinear overlapping, collinear apart
+        assert!(segments_intersect(&[0., 0.], &[2., 0.], &[1., 0.], &[3., 0.]));
+        assert!(!segments_intersect(&[0., 0.], &[1., 0.], &[2., 0.], &[3., 0.]));
+        //touching end points, end point on interior
+        assert!(segments_intersect(&[0., 0.], &[1., 1.], &[1., 1.], &[2., 0.]));
+        assert!(segments_intersect(&[0., 0.], &[2., 0.], &[1., 0.], &[1., 1.]));
+        //parallel, and a miss past the end of a segment
+        assert!(!segments_intersect(&[0., 0.], &[2., 0.], &[0., 1.], &[2., 1.]));
+        assert!(!segments_intersect(&[0., 0.], &[1., 0.], &[2., -1.], &[2., 1.]));
+    }
+
+    #[test]
+    fn test_point_in_polygon() {
+        let sq = square();
+        assert!(point_in_polygon(&[2., 2.], &sq));
+        assert!(point_in_polygon(&[4., 2.], &sq));
+        assert!(point_in_polygon(&[0., 0.], &sq));
+        assert!(!point_in_polygon(&[5., 2.], &sq));
+
+        let mut ring = concave();
+        assert!(!point_in_polygon(&[3., 4.], &ring));
+        assert!(point_in_polygon(&[1., 4.], &ring));
+        assert!(point_in_polygon(&[5., 4.], &ring));
+        assert!(point_in_polygon(&[3., 1.], &ring));
+        assert!(point_in_polygon(&[3., 2.], &ring));
+        ring.push(ring[0]);
+        assert!(!point_in_polygon(&[3., 4.], &ring));
+        assert!(point_in_polygon(&[1., 4.], &ring));
+    }
+
+    #[test]
+    fn test_envelope_polygon_relation() {
+        let sq = square();
+        let env = |a: [f64; 2], b: [f64; 2]| AABB::from_corners(a, b);
+        //polygon fully inside envelope
+        assert_eq!(envelope_polygon_relation(&env([-1., -1.], [10., 10.]), &sq), Relation::Intersects);
+        assert_eq!(envelope_polygon_relation(&env([1., 1.], [2., 2.]), &sq), Relation::Within);
+        assert_eq!(envelope_polygon_relation(&env([3., 3.], [5., 5.]), &sq), Relation::Intersects);
+        //touching at a corner
+        assert_eq!(envelope_polygon_relation(&env([4., 4.], [5., 5.]), &sq), Relation::Intersects);
+        assert_eq!(envelope_polygon_relation(&env([5., 5.], [6., 6.]), &sq), Relation::Disjoint);
+        //inside the notch of a concave ring
+        assert_eq!(envelope_polygon_relation(&env([2.5, 3.], [3.5, 5.]), &concave()), Relation::Disjoint);
+        assert_eq!(envelope_polygon_relation(&env([0.5, 3.], [1.5, 5.]), &concave()), Relation::Within);
+        assert_eq!(envelope_polygon_relation(&env([0., 0.], [1., 1.]), &[]), Relation::Disjoint);
+    }
+
+    #[test]
+    fn test_polyline_segments_and_envelope() {
+        let envelope = AABB::from_corners([0., 0.], [2., 2.]);
+        let segments = |line: &[[f64; 2]]| polyline_segments(line).map(|(a, b)| (*a, *b)).collect::<Vec<_>>();
+        assert_eq!(segments(&[[1., 1.]]), vec![([1., 1.], [1., 1.])]);
+        assert_eq!(segments(&[[0., 0.], [1., 1.], [2., 0.]]), vec![([0., 0.], [1., 1.]), ([1., 1.], [2., 0.])]);
+        assert!(segments(&[]).is_empty());
+
+        //crossing the envelope with both end points outside
+        assert!(segment_intersects_envelope(&[-1., 1.], &[3., 1.], &envelope));
+        //touching a corner, passing by a corner
+        assert!(segment_intersects_envelope(&[2., 2.], &[3., 3.], &envelope));
+        assert!(!segment_intersects_envelope(&[2., 3.], &[3., 2.], &envelope));
+        //zero-length segments
+        assert!(segment_intersects_envelope(&[1., 1.], &[1., 1.], &envelope));
+        assert!(!segment_intersects_envelope(&[3., 3.], &[3., 3.], &envelope));
+
+        assert_eq!(segment_envelope_distance(&[-1., 1.], &[3., 1.], &envelope), 0.0);
+        assert_eq!(segment_envelope_distance(&[5., 0.], &[5., 2.], &envelope), 3.0);
+        assert_eq!(segment_envelope_distance(&[5., 6.], &[5., 6.], &envelope), 5.0);
+        assert_eq!(segment_envelope_distance(&[2., 4.], &[4., 2.], &envelope), 2f64.sqrt());
+    }
+
+    #[test]
+    fn test_ray_envelope_entry() {
+        let envelope = AABB::from_corners([0., 0.], [2., 2.]);
+        let entry = |o: [f64; 2], d: [f64; 2], max_t: f64| ray_envelope_entry(&o, &d, &envelope, max_t);
+        //origin inside the box
+        assert_eq!(entry([1., 1.], [1., 0.], 10.), Some(0.));
+        assert_eq!(entry([1., 1.], [0., 0.], 10.), Some(0.));
+        //zero direction component: inside or outside the slab of that axis
+        assert_eq!(entry([-1., 1.], [1., 0.], 10.), Some(1.));
+        assert_eq!(entry([-1., 3.], [1., 0.], 10.), None);
+        assert_eq!(entry([-1., 1.], [0., 0.], 10.), None);
+        //negative direction, box behind origin, box beyond max_t
+        assert_eq!(entry([3., 1.], [-1., 0.], 10.), Some(1.));
+        assert_eq!(entry([3., 1.], [1., 0.], 10.), None);
+        assert_eq!(entry([-1., 1.], [1., 0.], 0.5), None);
+        assert_eq!(entry([-1., -2.], [1., 1.], 10.), Some(2.));
+    }
+
+    #[test]
+    fn test_envelope_within_distance() {
+        let a = AABB::from_corners([0., 0.], [1., 1.]);
+        let b = AABB::from_corners([4., 5.], [6., 6.]);
+        assert_eq!(envelope_gap(&a, &b), 4.0);
+        assert!(!envelope_within_distance(&a, &b, 4.5));
+        assert!(!envelope_within_distance(&a, &b, 5.0));
+        assert!(envelope_within_distance(&a, &b, 5.1));
+        assert!(envelope_within_distance(&a, &b, std::f64::INFINITY));
+        assert!(envelope_within_distance(&a, &a, 0.1));
+
+        let c = AABB::from_corners([0i64, 0], [1, 1]);
+        let d = AABB::from_corners([4i64, 5], [6, 6]);
+        assert!(!envelope_within_distance(&c, &d, 5));
+        assert!(envelope_within_distance(&c, &d, 6));
+        assert!(envelope_within_distance(&c, &d, std::i64::MAX));
+    }
+}
@@ -0,0 +1,32 @@
+use std::slice;
+use rstar::{RTreeObject, RTreeNode};
+
+///
+///Mutable iterator over items in tree
+///
+pub struct IterMut<'a, T> where T: RTreeObject {
+    stack: Vec<slice::IterMut<'a, RTreeNode<T>>>,
+}
+
+impl<'a, T> IterMut<'a, T> where T: RTreeObject {
+    pub(crate) fn new(children: &'a mut Vec<RTreeNode<T>>) -> Self {
+        IterMut { stack: vec![children.iter_mut()] }
+    }
+}
+
+impl<'a, T> Iterator for IterMut<'a, T> where T: RTreeObject {
+    type Item = &'a mut T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(children) = self.stack.last_mut() {
+            match children.next() {
+                Some(RTreeNode::Leaf(item)) => return Some(item),
+                Some(RTreeNode::Parent(nd)) => self.stack.push(nd.children.iter_mut()),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,161 @@
+use std::slice;
+use std::collections::BinaryHeap;
+use rstar::{RTreeObject, RTreeNode, Envelope};
+use crate::{RTree, KObj, Scalar};
+use crate::geom;
+
+///Queue node pairs accepted by predicate
+fn push_pairs<'a, 'b, T, U>(stack: &mut Vec<(&'a RTreeNode<T>, &'b RTreeNode<U>)>,
+                            xs: &'a [RTreeNode<T>], ys: &'b [RTreeNode<U>],
+                            predicate: &impl Fn(&T::Envelope, &T::Envelope) -> bool)
+    where T: RTreeObject,
+          U: RTreeObject<Envelope=T::Envelope> {
+    for x in xs.iter() {
+        let x_env = x.envelope();
+        for y in ys.iter() {
+            if predicate(&x_env, &y.envelope()) {
+                stack.push((x, y));
+            }
+        }
+    }
+}
+
+///Expand queued node pairs down to item pairs accepted by predicate
+fn join_pairs<'a, 'b, T, U>(mut stack: Vec<(&'a RTreeNode<T>, &'b RTreeNode<U>)>,
+                            predicate: &impl Fn(&T::Envelope, &T::Envelope) -> bool) -> Vec<(&'a T, &'b U)>
+    where T: RTreeObject,
+          U: RTreeObject<Envelope=T::Envelope> {
+    let mut results = vec![];
+    while let Some(pair) = stack.pop() {
+        match pair {
+            (RTreeNode::Leaf(ref a), RTreeNode::Leaf(ref b)) => {
+                results.push((a, b));
+            }
+            (RTreeNode::Leaf(_), RTreeNode::Parent(ref q)) => {
+                push_pairs(&mut stack, slice::from_ref(pair.0), q.children(), predicate);
+            }
+            (RTreeNode::Parent(ref p), RTreeNode::Leaf(_)) => {
+                push_pairs(&mut stack, p.children(), slice::from_ref(pair.1), predicate);
+            }
+            (RTreeNode::Parent(ref p), RTreeNode::Parent(ref q)) => {
+                push_pairs(&mut stack, p.children(), q.children(), predicate);
+            }
+        }
+    }
+    results
+}
+
+///Queue node pairs by squared distance: `dist_fn` for item pairs,
+///squared envelope distance otherwise
+fn queue_pairs<'a, 'b, T, U>(queue: &mut BinaryHeap<KObj<T::Envelope>>,
+                             pairs: &mut Vec<(&'a RTreeNode<T>, &'b RTreeNode<U>)>,
+                             xs: &'a [RTreeNode<T>], ys: &'b [RTreeNode<U>],
+                             dist_fn: &impl Fn(&T, &U) -> Scalar<T>)
+    where T: RTreeObject,
+          U: RTreeObject<Envelope=T::Envelope> {
+    for x in xs.iter() {
+        let x_env = x.envelope();
+        for y in ys.iter() {
+            let y_env = y.envelope();
+            let distance = match (x, y) {
+                (RTreeNode::Leaf(ref a), RTreeNode::Leaf(ref b)) => dist_fn(a, b),
+                _ => geom::envelope_distance_2(&x_env, &y_env),
+            };
+            queue.push(KObj {
+                distance,
+                is_item: x.is_leaf() && y.is_leaf(),
+                mbr: x_env.merged(&y_env),
+                node: pairs.len(),
+            });
+            pairs.push((x, y));
+        }
+    }
+}
+
+impl<T> RTree<T> where T: RTreeObject {
+    ///Pairs of items from both trees whose envelopes are accepted by `predicate`,
+    ///by synchronized traversal of both trees. `predicate` must accept the envelopes
+    ///of parent nodes if it accepts any of their descendants (e.g. intersection).
+    pub fn join<'a, 'b, U>(&'a self, other: &'b RTree<U>,
+                           predicate: impl Fn(&T::Envelope, &T::Envelope) -> bool) -> Vec<(&'a T, &'b U)>
+        where U: RTreeObject<Envelope=T::Envelope> {
+        let mut stack = vec![];
+        push_pairs(&mut stack, self.index.root().children(), other.index.root().children(), &predicate);
+        join_pairs(stack, &predicate)
+    }
+
+    ///Pairs of items from both trees with intersecting envelopes
+    pub fn join_intersecting<'a, 'b, U>(&'a self, other: &'b RTree<U>) -> Vec<(&'a T, &'b U)>
+        where U: RTreeObject<Envelope=T::Envelope> {
+        self.join(other, |a, b| a.intersects(b))
+    }
+
+    ///Pairs of items from both trees with envelopes within distance
+    pub fn join_within_distance<'a, 'b, U>(&'a self, other: &'b RTree<U>, dist: Scalar<T>) -> Vec<(&'a T, &'b U)>
+        where U: RTreeObject<Envelope=T::Envelope> {
+        self.join(other, |a, b| geom::envelope_within_distance_inclusive(a, b, dist))
+    }
+
+    ///Unordered pairs of distinct items whose envelopes are accepted by `predicate`,
+    ///each pair is reported once. `predicate` must be symmetric and accept the envelopes
+    ///of parent nodes if it accepts any of their descendants (e.g. intersection).
+    pub fn self_join(&self, predicate: impl Fn(&T::Envelope, &T::Envelope) -> bool) -> Vec<(&T, &T)> {
+        let mut stack = vec![];
+        let mut groups = vec![self.index.root().children()];
+        //pair siblings, nodes of distinct subtrees never share items
+        while let Some(children) = groups.pop() {
+            for (i, child) in children.iter().enumerate() {
+                if let RTreeNode::Parent(ref p) = child {
+                    groups.push(p.children());
+                }
+                push_pairs(&mut stack, slice::from_ref(child), &children[i + 1..], &predicate);
+            }
+        }
+        join_pairs(stack, &predicate)
+    }
+
+    ///Unordered pairs of distinct items with intersecting envelopes
+    pub fn self_join_intersecting(&self) -> Vec<(&T, &T)> {
+        self.self_join(|a, b| a.intersects(b))
+    }
+
+    ///Unordered pairs of distinct items with envelopes within distance
+    pub fn self_join_within_distance(&self, dist: Scalar<T>) -> Vec<(&T, &T)> {
+        self.self_join(|a, b| geom::envelope_within_distance_inclusive(a, b, dist))
+    }
+
+    ///k closest pairs of items from both trees and their squared distances, in order of
+    ///distance, by dual-tree best-first traversal; a `limit` of 0 is unbounded. Node pairs
+    ///are ranked by their squared envelope distance, `dist_fn` is a squared distance and
+    ///must not be less than the squared distance between the envelopes of its items.
+    pub fn closest_pairs<'a, 'b, U>(&'a self, other: &'b RTree<U>, limit: usize,
+                                    dist_fn: impl Fn(&T, &U) -> Scalar<T>) -> Vec<(&'a T, &'b U, Scalar<T>)>
+        where U: RTreeObject<Envelope=T::Envelope> {
+        let mut results = vec![];
+        let mut pairs = vec![];
+        let mut queue = BinaryHeap::new();
+        queue_pairs(&mut queue, &mut pairs, self.index.root().children(), other.index.root().children(), &dist_fn);
+
+        while let Some(candidate) = queue.pop() {
+            let pair = pairs[candidate.node];
+            match pair {
+                (RTreeNode::Leaf(ref a), RTreeNode::Leaf(ref b)) => {
+                    results.push((a, b, candidate.distance));
+                    if limit != 0 && results.len() == limit {
+                        break;
+                    }
+                }
+                (RTreeNode::Leaf(_), RTreeNode::Parent(ref q)) => {
+                    queue_pairs(&mut queue, &mut pairs, slice::from_ref(pair.0), q.children(), &dist_fn);
+                }
+                (RTreeNode::Parent(ref p), RTreeNode::Leaf(_)) => {
+                    queue_pairs(&mut queue, &mut pairs, p.children(), slice::from_ref(pair.1), &dist_fn);
+                }
+                (RTreeNode::Parent(ref p), RTreeNode::Parent(ref q)) => {
+                    queue_pairs(&mut queue, &mut pairs, p.children(), q.children(), &dist_fn);
+                }
+            }
+        }
+        results
+    }
+}
@@ -0,0 +1,125 @@
+use std::collections::BinaryHeap;
+use math_util::num::Zero;
+use rstar::{RTreeObject, RTreeNode, ParentNode};
+use crate::{KObj, RTree, Scalar};
+use crate::QueueObj;
+
+///
+///Control action of a knn predicate on a candidate item
+///
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KnnControl {
+    ///Add candidate to results, continue search
+    Accept,
+    ///Ignore candidate, continue search
+    Skip,
+    ///Add candidate to results, stop search
+    AcceptAndStop,
+    ///Ignore candidate, stop search
+    Stop,
+}
+
+impl KnnControl {
+    #[inline]
+    pub fn is_accept(&self) -> bool {
+        match self {
+            KnnControl::Accept | KnnControl::AcceptAndStop => true,
+            KnnControl::Skip | KnnControl::Stop => false,
+        }
+    }
+
+    #[inline]
+    pub fn is_stop(&self) -> bool {
+        match self {
+            KnnControl::AcceptAndStop | KnnControl::Stop => true,
+            KnnControl::Accept | KnnControl::Skip => false,
+        }
+    }
+}
+
+///
+///Lazy best-first kNN iterator over the nodes of an `RTree`,
+///the queue entry `O` orders the traversal (nearest first by default)
+///
+pub struct KnnIter<'a, 'q, T, Q, F, P, O = KObj<<T as RTreeObject>::Envelope>> where T: RTreeObject, Q: ?Sized {
+    query: &'q Q,
+    fn_dist_score: F,
+    fn_prune: P,
+    queue: BinaryHeap<O>,
+    parents: Vec<&'a ParentNode<T>>,
+    leaves: Vec<&'a T>,
+}
+
+impl<'a, 'q, T, Q, F, P, O> KnnIter<'a, 'q, T, Q, F, P, O>
+    where T: RTreeObject + Clone,
+          Q: ?Sized,
+          F: Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>,
+          P: Fn(KObj<T::Envelope>) -> bool,
+          O: QueueObj<T::Envelope> {
+    pub(crate) fn new(tree: &'a RTree<T>, query: &'q Q, fn_dist_score: F, fn_prune: P) -> Self {
+        let mut iter = KnnIter {
+            query,
+            fn_dist_score,
+            fn_prune,
+            queue: BinaryHeap::new(),
+            parents: vec![],
+            leaves: vec![],
+        };
+        iter.expand(tree.index.root());
+        iter
+    }
+
+    ///Queue children of node, parents rejected by `fn_prune` are not expanded
+    fn expand(&mut self, nd: &'a ParentNode<T>) {
+        for child in nd.children().iter() {
+            let mut o = KObj {
+                distance: Zero::zero(),
+                is_item: child.is_leaf(),
+                mbr: child.envelope(),
+                node: RTree::<T>::max_sentinel(),
+            };
+            match child {
+                RTreeNode::Leaf(ref item) => {
+                    o.distance = (self.fn_dist_score)(self.query, Some(item), o);
+                    o.node = self.leaves.len();
+                    self.leaves.push(item);
+                }
+                RTreeNode::Parent(ref p) => {
+                    o.distance = (self.fn_dist_score)(self.query, None, o);
+                    if (self.fn_prune)(o) {
+                        continue;
+                    }
+                    o.node = self.parents.len();
+                    self.parents.push(p);
+                }
+            }
+            self.queue.push(O::from(o))
+        }
+    }
+
+    ///Next item candidate in order of score
+    pub(crate) fn next_candidate(&mut self) -> Option<(&'a T, KObj<T::Envelope>)> {
+        while let Some(entry) = self.queue.pop() {
+            let candidate = entry.kobj();
+            if candidate.is_item {
+                return Some((self.leaves[candidate.node], candidate));
+            }
+            let nd = self.parents[candidate.node];
+            self.expand(nd);
+        }
+        None
+    }
+}
+
+impl<'a, 'q, T, Q, F, P, O> Iterator for KnnIter<'a, 'q, T, Q, F, P, O>
+    where T: RTreeObject + Clone,
+          Q: ?Sized,
+          F: Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>,
+          P: Fn(KObj<T::Envelope>) -> bool,
+          O: QueueObj<T::Envelope> {
+    type Item = (&'a T, Scalar<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_candidate().map(|(item, o)| (item, o.distance))
+    }
+}
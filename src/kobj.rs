@@ -1,60 +1,143 @@
 use std::cmp::Ordering;
-use std::collections::BinaryHeap;
-use bbox_2d::MBR;
-use math_util::Feq;
+use rstar::{Envelope, Point};
+use crate::geom;
 
 ///
 ///Min - Heap KObj
 ///
 #[derive(Clone, Copy, Debug)]
-pub struct KObj {
-    pub distance: f64,
+pub struct KObj<E> where E: Envelope {
+    pub distance: <E::Point as Point>::Scalar,
     pub is_item: bool,
-    pub mbr: MBR,
+    pub mbr: E,
     pub node: usize,
 }
 
-impl Eq for KObj {}
+impl<E> Eq for KObj<E> where E: Envelope {}
 
-impl PartialEq for KObj {
+impl<E> PartialEq for KObj<E> where E: Envelope {
     fn eq(&self, other: &Self) -> bool {
-        self.distance.feq(other.distance)
+        self.cmp(other) == Ordering::Equal
     }
 }
 
-impl PartialOrd for KObj {
+impl<E> PartialOrd for KObj<E> where E: Envelope {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        other.distance.partial_cmp(&self.distance)
+        Some(self.cmp(other))
     }
 }
 
-impl Ord for KObj where {
-    fn cmp(&self, other: &KObj) -> Ordering {
-        self.partial_cmp(other).unwrap()
+///Total order of min-heap: smaller distance first, NaN distances last;
+///on ties, items before parent nodes, then in the order the traversal
+///discovers them (`node`)
+impl<E> Ord for KObj<E> where E: Envelope {
+    fn cmp(&self, other: &KObj<E>) -> Ordering {
+        total_cmp(other.distance, self.distance)
+            .then_with(|| self.is_item.cmp(&other.is_item))
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+///
+///Max - Heap KObj
+///
+#[derive(Clone, Copy, Debug)]
+pub struct FarObj<E>(pub KObj<E>) where E: Envelope;
+
+impl<E> Eq for FarObj<E> where E: Envelope {}
+
+impl<E> PartialEq for FarObj<E> where E: Envelope {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<E> PartialOrd for FarObj<E> where E: Envelope {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+///Total order of max-heap: greater distance first, NaN distances last;
+///on ties, items before parent nodes, then in the order the traversal
+///discovers them (`node`)
+impl<E> Ord for FarObj<E> where E: Envelope {
+    fn cmp(&self, other: &FarObj<E>) -> Ordering {
+        let (a, b) = (&self.0, &other.0);
+        total_cmp_nan_least(a.distance, b.distance)
+            .then_with(|| a.is_item.cmp(&b.is_item))
+            .then_with(|| b.node.cmp(&a.node))
+    }
+}
+
+///Priority queue entry of a best-first traversal
+pub trait QueueObj<E>: Ord + Copy + From<KObj<E>> where E: Envelope {
+    fn kobj(&self) -> KObj<E>;
+}
+
+impl<E> QueueObj<E> for KObj<E> where E: Envelope {
+    fn kobj(&self) -> KObj<E> {
+        *self
+    }
+}
+
+impl<E> From<KObj<E>> for FarObj<E> where E: Envelope {
+    fn from(o: KObj<E>) -> Self {
+        FarObj(o)
+    }
+}
+
+impl<E> QueueObj<E> for FarObj<E> where E: Envelope {
+    fn kobj(&self) -> KObj<E> {
+        self.0
+    }
+}
+
+///Compare distances, NaN is greater than any number and equal to NaN
+#[inline]
+fn total_cmp<S>(a: S, b: S) -> Ordering where S: PartialOrd {
+    match a.partial_cmp(&b) {
+        Some(o) => o,
+        None => geom::is_nan(a).cmp(&geom::is_nan(b)),
+    }
+}
+
+///Compare distances, NaN is less than any number and equal to NaN
+#[inline]
+fn total_cmp_nan_least<S>(a: S, b: S) -> Ordering where S: PartialOrd {
+    match a.partial_cmp(&b) {
+        Some(o) => o,
+        None => geom::is_nan(b).cmp(&geom::is_nan(a)),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::collections::BinaryHeap;
+    use rstar::AABB;
+
+    fn env() -> AABB<[f64; 2]> {
+        AABB::from_point([0.0, 0.0])
+    }
 
     #[test]
     fn test_min_heap() {
         let mut minheap = BinaryHeap::new();
-        minheap.push(KObj { distance: 0.3, is_item: false, mbr: MBR::new_default(), node: 0 });
-        minheap.push(KObj { distance: 42.0, is_item: false, mbr: MBR::new_default(), node: 0 });
-        minheap.push(KObj { distance: 0.42, is_item: false, mbr: MBR::new_default(), node: 0 });
-        minheap.push(KObj { distance: 1.0, is_item: false, mbr: MBR::new_default(), node: 0 });
-        minheap.push(KObj { distance: 0.3, is_item: false, mbr: MBR::new_default(), node: 0 });
-        minheap.push(KObj { distance: 0.42, is_item: false, mbr: MBR::new_default(), node: 0 });
-        minheap.push(KObj { distance: 0.43, is_item: false, mbr: MBR::new_default(), node: 0 });
+        minheap.push(KObj { distance: 0.3, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 42.0, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 0.42, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 1.0, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 0.3, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 0.42, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 0.43, is_item: false, mbr: env(), node: 0 });
         let o = minheap.pop();
         assert!(o.is_some());
-        assert_eq!(o.unwrap(), KObj { distance: 0.3, is_item: false, mbr: MBR::new_default(), node: 0 });
+        assert_eq!(o.unwrap(), KObj { distance: 0.3, is_item: false, mbr: env(), node: 0 });
 
         let o = minheap.pop();
         assert!(o.is_some());
-        assert_eq!(o.unwrap(), KObj { distance: 0.3, is_item: false, mbr: MBR::new_default(), node: 0 });
+        assert_eq!(o.unwrap(), KObj { distance: 0.3, is_item: false, mbr: env(), node: 0 });
 
         let o = minheap.pop();
         assert!(o.is_some());
@@ -79,4 +162,45 @@ mod tests {
         let o = minheap.pop();
         assert!(o.is_none());
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_min_heap_tie_breaking() {
+        let mut minheap = BinaryHeap::new();
+        minheap.push(KObj { distance: 1.0, is_item: false, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: std::f64::NAN, is_item: true, mbr: env(), node: 0 });
+        minheap.push(KObj { distance: 1.0, is_item: true, mbr: env(), node: 2 });
+        minheap.push(KObj { distance: 1.0, is_item: true, mbr: env(), node: 1 });
+        minheap.push(KObj { distance: 1.0, is_item: false, mbr: env(), node: 1 });
+        minheap.push(KObj { distance: 2.0, is_item: true, mbr: env(), node: 3 });
+
+        let order = (0..6).map(|_| {
+            let o = minheap.pop().unwrap();
+            (o.is_item, o.node)
+        }).collect::<Vec<_>>();
+        assert_eq!(order, vec![(true, 1), (true, 2), (false, 0), (false, 1), (true, 3), (true, 0)]);
+        assert!(minheap.pop().is_none());
+
+        let a = KObj { distance: std::f64::NAN, is_item: true, mbr: env(), node: 0 };
+        assert_eq!(a, a);
+        assert_eq!(a.cmp(&a), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_max_heap_tie_breaking() {
+        let mut maxheap = BinaryHeap::new();
+        maxheap.push(FarObj(KObj { distance: 1.0, is_item: false, mbr: env(), node: 0 }));
+        maxheap.push(FarObj(KObj { distance: std::f64::NAN, is_item: true, mbr: env(), node: 0 }));
+        maxheap.push(FarObj(KObj { distance: 1.0, is_item: true, mbr: env(), node: 2 }));
+        maxheap.push(FarObj(KObj { distance: 1.0, is_item: true, mbr: env(), node: 1 }));
+        maxheap.push(FarObj(KObj { distance: 1.0, is_item: false, mbr: env(), node: 1 }));
+        maxheap.push(FarObj(KObj { distance: 0.5, is_item: true, mbr: env(), node: 3 }));
+        maxheap.push(FarObj(KObj { distance: 2.0, is_item: false, mbr: env(), node: 4 }));
+
+        let order = (0..7).map(|_| {
+            let o = maxheap.pop().unwrap().0;
+            (o.is_item, o.node)
+        }).collect::<Vec<_>>();
+        assert_eq!(order, vec![(false, 4), (true, 1), (true, 2), (false, 0), (false, 1), (true, 3), (true, 0)]);
+        assert!(maxheap.pop().is_none());
+    }
+}
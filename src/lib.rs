@@ -1,4 +1,13 @@
 mod kobj;
+mod knn;
+mod geom;
+mod error;
+mod iter;
+mod search;
+mod ray;
+mod join;
+mod farthest;
+pub mod linestring;
 
 pub use rstar::{
     RTreeObject,
@@ -10,12 +19,19 @@ pub use rstar::{
     AABB,
 };
 use std::collections::BinaryHeap;
-use bbox_2d::MBR;
-use math_util::num;
+use math_util::num::Zero;
 use rstar::RTreeNode;
 use std::mem;
+pub use std::ops::ControlFlow;
 
-pub use kobj::KObj;
+pub use kobj::{KObj, FarObj, QueueObj};
+pub use knn::{KnnIter, KnnControl};
+pub use error::{Error, Result};
+pub use iter::IterMut;
+pub use geom::{seg_seg_distance, distance_to_point};
+
+///Scalar type of the envelope of T
+pub type Scalar<T> = <<<T as RTreeObject>::Envelope as Envelope>::Point as Point>::Scalar;
 
 
 #[derive(Clone, Debug)]
@@ -34,20 +50,66 @@ impl<T> RTree<T> where T: RTreeObject + PartialEq {
 }
 
 impl<T> RTree<T> where T: RTreeObject + PointDistance {
-    pub fn nearest_neighbor(&self, query_pt: &<T::Envelope as Envelope>::Point) -> Option<&T> {
-        self.index.nearest_neighbor(query_pt)
+    ///Nearest item to query point and its squared distance
+    pub fn nearest_neighbor(&self, query_pt: &<T::Envelope as Envelope>::Point)
+                            -> Option<(&T, Scalar<T>)> {
+        self.index.nearest_neighbor(query_pt).map(|o| (o, o.distance_2(query_pt)))
     }
 
+    ///Items within squared radius of query point and their squared distances
     pub fn locate_within_distance(
         &self,
         query_pt: <T::Envelope as Envelope>::Point,
-        sqr_radius: <<T::Envelope as Envelope>::Point as rstar::Point>::Scalar,
-    ) -> Vec<&T> {
-        let mut results = vec![];
-        for o in self.index.locate_within_distance(query_pt, sqr_radius) {
-            results.push(o);
+        sqr_radius: Scalar<T>,
+    ) -> Vec<(&T, Scalar<T>)> {
+        self.within_distance_iter(query_pt, sqr_radius).collect()
+    }
+
+    ///Lazy iterator of items within squared radius of query point
+    ///and their squared distances
+    pub fn within_distance_iter(
+        &self,
+        query_pt: <T::Envelope as Envelope>::Point,
+        sqr_radius: Scalar<T>,
+    ) -> impl Iterator<Item=(&T, Scalar<T>)> {
+        self.index.locate_within_distance(query_pt, sqr_radius)
+            .map(move |o| (o, o.distance_2(&query_pt)))
+    }
+
+    ///Nearest item to query point and its squared distance,
+    ///fails on an empty tree or a non-finite query point
+    pub fn try_nearest_neighbor(&self, query_pt: &<T::Envelope as Envelope>::Point)
+                                -> Result<(&T, Scalar<T>)> {
+        if !geom::is_finite_point(query_pt) {
+            return Err(Error::NonFiniteCoordinate);
         }
-        results
+        self.nearest_neighbor(query_pt).ok_or(Error::EmptyIndex)
+    }
+
+    ///Items within squared radius of query point as `locate_within_distance`,
+    ///fails on a non-finite query point or a NaN radius
+    pub fn try_locate_within_distance(
+        &self,
+        query_pt: <T::Envelope as Envelope>::Point,
+        sqr_radius: Scalar<T>,
+    ) -> Result<Vec<(&T, Scalar<T>)>> {
+        self.try_within_distance_iter(query_pt, sqr_radius).map(|iter| iter.collect())
+    }
+
+    ///Lazy iterator of items within squared radius of query point as
+    ///`within_distance_iter`, fails on a non-finite query point or a NaN radius
+    pub fn try_within_distance_iter(
+        &self,
+        query_pt: <T::Envelope as Envelope>::Point,
+        sqr_radius: Scalar<T>,
+    ) -> Result<impl Iterator<Item=(&T, Scalar<T>)>> {
+        if !geom::is_finite_point(&query_pt) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        if geom::is_nan(sqr_radius) {
+            return Err(Error::InvalidDistance);
+        }
+        Ok(self.within_distance_iter(query_pt, sqr_radius))
     }
 
     pub fn remove_at_point(&mut self, pt: &<T::Envelope as Envelope>::Point) -> Option<T> {
@@ -64,16 +126,32 @@ impl<T> RTree<T> where T: RTreeObject + Clone {
         RTree { index: Index::bulk_load(items) }
     }
 
+    ///Bulk load items, fails if an item envelope has a non-finite coordinate
+    pub fn try_load(items: Vec<T>) -> Result<Self> {
+        if !items.iter().all(|o| geom::is_finite(&o.envelope())) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        Ok(RTree::load(items))
+    }
+
     pub fn root(&self) -> &ParentNode<T> {
         self.index.root()
     }
 
-    pub fn each(&self, func: fn(&T)) {
+    ///Visit items in tree, stops on `ControlFlow::Break`
+    pub fn each(&self, mut func: impl FnMut(&T) -> ControlFlow<()>) {
         for item in self.index.iter() {
-            func(item);
+            if func(item).is_break() {
+                break;
+            }
         }
     }
 
+    ///Iterator over items in tree
+    pub fn iter(&self) -> impl Iterator<Item=&T> {
+        self.index.iter()
+    }
+
     pub fn rtree(&self) -> Index<T> {
         self.index.clone()
     }
@@ -82,6 +160,15 @@ impl<T> RTree<T> where T: RTreeObject + Clone {
         self.index.insert(item)
     }
 
+    ///Insert item, fails if item envelope has a non-finite coordinate
+    pub fn try_insert(&mut self, item: T) -> Result<()> {
+        if !geom::is_finite(&item.envelope()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        self.insert(item);
+        Ok(())
+    }
+
 
     pub fn size(&self) -> usize {
         self.index.size()
@@ -96,22 +183,50 @@ impl<T> RTree<T> where T: RTreeObject + Clone {
     }
 
     pub fn search(&self, envelope: &T::Envelope) -> Vec<&T> {
-        let elements = self.index.locate_in_envelope_intersecting(
-            envelope
-        );
-        let mut results = vec![];
-        for o in elements.into_iter() {
-            results.push(o)
+        self.search_iter(envelope).collect()
+    }
+
+    ///Lazy iterator of items intersecting envelope
+    pub fn search_iter(&self, envelope: &T::Envelope) -> impl Iterator<Item=&T> {
+        self.index.locate_in_envelope_intersecting(envelope)
+    }
+
+    ///Items intersecting envelope, fails if envelope has a non-finite coordinate
+    pub fn try_search(&self, envelope: &T::Envelope) -> Result<Vec<&T>> {
+        self.try_search_iter(envelope).map(|iter| iter.collect())
+    }
+
+    ///Lazy iterator of items intersecting envelope,
+    ///fails if envelope has a non-finite coordinate
+    pub fn try_search_iter(&self, envelope: &T::Envelope) -> Result<impl Iterator<Item=&T>> {
+        if !geom::is_finite(envelope) {
+            return Err(Error::NonFiniteCoordinate);
         }
-        results
+        Ok(self.search_iter(envelope))
+    }
+
+    ///Number of items intersecting envelope
+    pub fn count_in_envelope(&self, envelope: &T::Envelope) -> usize {
+        self.search_iter(envelope).count()
+    }
+
+    ///Any item intersects envelope, stops at first hit
+    pub fn any_in_envelope(&self, envelope: &T::Envelope) -> bool {
+        self.search_iter(envelope).next().is_some()
     }
 
     ///Consume RTree<T> and return all objects in tree
     pub fn all(mut self) -> Vec<T> {
+        self.drain()
+    }
+
+    ///Remove and return all objects in tree, leaves tree empty
+    pub fn drain(&mut self) -> Vec<T> {
         let mut vals = Vec::with_capacity(self.size());
 
+        let mut index = mem::replace(&mut self.index, Index::new());
         let mut children = vec![];
-        mem::swap(&mut self.index.root.children, &mut children);
+        mem::swap(&mut index.root.children, &mut children);
         let mut data = vec![children];
 
         while data.len() > 0 {
@@ -131,6 +246,40 @@ impl<T> RTree<T> where T: RTreeObject + Clone {
         }
         vals
     }
+
+    ///Mutable iterator over items in tree, the envelope of an item
+    ///must not change, see `modify` to mutate spatial fields
+    pub fn iter_mut(&mut self) -> IterMut<T> {
+        IterMut::new(&mut self.index.root.children)
+    }
+
+    ///Mutate items in tree, items whose envelope changed are re-inserted,
+    ///returns the number of re-inserted items. `func` mutates a scratch copy
+    ///of each item, items left unchanged are not written back.
+    pub fn modify(&mut self, mut func: impl FnMut(&mut T)) -> usize where T: PartialEq {
+        let mut moved = vec![];
+        let mut scratch: Option<T> = None;
+        for item in self.iter_mut() {
+            if let Some(o) = scratch.as_mut() {
+                o.clone_from(item);
+            } else {
+                scratch = Some(item.clone());
+            }
+            let o = scratch.as_mut().unwrap();
+            func(o);
+            if o.envelope() != item.envelope() {
+                moved.push((item.clone(), scratch.take().unwrap()));
+            } else if *o != *item {
+                mem::swap(item, o);
+            }
+        }
+        let n = moved.len();
+        for (old, new) in moved.into_iter() {
+            self.index.remove(&old);
+            self.index.insert(new);
+        }
+        n
+    }
 }
 
 impl<T> RTree<T> where T: RTreeObject {
@@ -140,107 +289,176 @@ impl<T> RTree<T> where T: RTreeObject {
     }
 
     #[inline]
-    pub fn knn_predicate(_: KObj) -> (bool, bool) {
-        (true, false)
+    pub fn knn_predicate(_: &T, _: KObj<T::Envelope>) -> KnnControl {
+        KnnControl::Accept
+    }
+
+    #[inline]
+    pub fn knn_no_prune(_: KObj<T::Envelope>) -> bool {
+        false
     }
 }
 
 impl<T> RTree<T> where T: RTreeObject + Clone {
-    fn env_mbr(envelope: &T::Envelope) -> MBR {
-        let ll = envelope.lower_left();
-        let ur = envelope.upper_right();
-        MBR::new(
-            num::cast(ll.nth(0)).unwrap(),
-            num::cast(ll.nth(1)).unwrap(),
-            num::cast(ur.nth(0)).unwrap(),
-            num::cast(ur.nth(1)).unwrap(),
-        )
+    ///Lazy kNN iterator of (item, score) in order of `fn_dist_score`,
+    ///query can be a point, an envelope or any other object
+    pub fn knn_iter<'a, 'q, Q, F>(&'a self, query: &'q Q, fn_dist_score: F)
+                                  -> KnnIter<'a, 'q, T, Q, F, fn(KObj<T::Envelope>) -> bool>
+        where Q: ?Sized,
+              F: Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T> {
+        self.knn_iter_pruned(query, fn_dist_score, Self::knn_no_prune as fn(KObj<T::Envelope>) -> bool)
     }
 
-    pub fn knn(&self, query: &T, limit: usize,
-               fn_dist_score: impl Fn(&T, Option<&T>, KObj) -> f64,
-               fn_predicate: impl Fn(KObj) -> (bool, bool)) -> Vec<&T> {
-        let mut result = vec![];
-        let mut parents = vec![Some(self.index.root())];
-        let mut leaves = vec![];
-        let mut nd = parents[0];
-        let mut pred;
-        let mut stop = false;
-        let mut queue = BinaryHeap::new();
-        let null_idx = Self::max_sentinel();
+    ///Lazy kNN iterator, parent nodes for which `fn_prune` returns true
+    ///are not expanded
+    pub fn knn_iter_pruned<'a, 'q, Q, F, P>(&'a self, query: &'q Q, fn_dist_score: F, fn_prune: P)
+                                            -> KnnIter<'a, 'q, T, Q, F, P>
+        where Q: ?Sized,
+              F: Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>,
+              P: Fn(KObj<T::Envelope>) -> bool {
+        KnnIter::new(self, query, fn_dist_score, fn_prune)
+    }
 
-        while !stop && nd.is_some() {
-            for child in nd.unwrap().children().iter() {
-                let mut o = KObj {
-                    distance: 0f64,
-                    is_item: child.is_leaf(),
-                    mbr: Self::env_mbr(&child.envelope()),
-                    node: null_idx,
-                };
-                match child {
-                    RTreeNode::Leaf(ref item) => {
-                        o.distance = fn_dist_score(query, Some(item), o);
-                        o.node = leaves.len();
-                        leaves.push(item);
-                    }
-                    RTreeNode::Parent(ref p) => {
-                        o.distance = fn_dist_score(query, None, o);
-                        o.node = parents.len();
-                        parents.push(Some(p));
-                    }
-                }
-                queue.push(o)
-            }
+    pub fn knn<Q>(&self, query: &Q, limit: usize,
+                  fn_dist_score: impl Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>,
+                  fn_predicate: impl Fn(&T, KObj<T::Envelope>) -> KnnControl) -> Vec<(&T, Scalar<T>)>
+        where Q: ?Sized {
+        self.knn_pruned(query, limit, fn_dist_score, fn_predicate, Self::knn_no_prune)
+    }
 
-            //check leaves
-            while !queue.is_empty() && queue.peek().unwrap().is_item {
-                let candidate = queue.pop().unwrap();
-                let pred_stop = fn_predicate(candidate);
-                pred = pred_stop.0;
-                stop = pred_stop.1;
+    ///knn with subtree pruning: parent nodes for which `fn_prune`
+    ///returns true (e.g. by `KObj.mbr` or `KObj.distance`) are not walked
+    pub fn knn_pruned<Q>(&self, query: &Q, limit: usize,
+                         fn_dist_score: impl Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>,
+                         fn_predicate: impl Fn(&T, KObj<T::Envelope>) -> KnnControl,
+                         fn_prune: impl Fn(KObj<T::Envelope>) -> bool) -> Vec<(&T, Scalar<T>)>
+        where Q: ?Sized {
+        let mut result = vec![];
+        let mut iter = self.knn_iter_pruned(query, fn_dist_score, fn_prune);
 
-                if pred {
-                    result.push(leaves[candidate.node]);
-                }
-                if stop {
-                    break;
-                }
-                if limit != 0 && result.len() == limit {
-                    return result;
-                }
+        while let Some((item, candidate)) = iter.next_candidate() {
+            let ctrl = fn_predicate(item, candidate);
+            if ctrl.is_accept() {
+                result.push((item, candidate.distance));
             }
-            //check parents
-            if !stop {
-                let q = queue.pop();
-                nd = if q.is_none() { None } else { parents[q.unwrap().node] };
+            if ctrl.is_stop() {
+                break;
+            }
+            if limit != 0 && result.len() == limit {
+                break;
             }
         }
         result
     }
 
 
-    pub fn knn_min_dist(&self, query: &T,
-                        fn_dist_score: impl Fn(&T, &T) -> f64,
-                        fn_predicate: impl Fn(KObj, f64) -> bool, mut mindist: f64) -> f64 {
+    ///k nearest items within `max_dist` of query, traversal stops once
+    ///the queue head exceeds `max_dist`; a `limit` of 0 is unbounded
+    pub fn knn_within<Q>(&self, query: &Q, limit: usize, max_dist: Scalar<T>,
+                         fn_dist_score: impl Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>) -> Vec<(&T, Scalar<T>)>
+        where Q: ?Sized {
+        self.knn_pruned(query, limit, fn_dist_score,
+                        |_, o| if o.distance > max_dist { KnnControl::Stop } else { KnnControl::Accept },
+                        |o| o.distance > max_dist,
+        )
+    }
+
+    ///Lazy kNN iterator as `knn_iter`, fails if query envelope has a non-finite coordinate
+    pub fn try_knn_iter<'a, 'q, Q, F>(&'a self, query: &'q Q, fn_dist_score: F)
+                                      -> Result<KnnIter<'a, 'q, T, Q, F, fn(KObj<T::Envelope>) -> bool>>
+        where Q: RTreeObject<Envelope=T::Envelope>,
+              F: Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T> {
+        if !geom::is_finite(&query.envelope()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        Ok(self.knn_iter(query, fn_dist_score))
+    }
+
+    ///k nearest items as `knn`, fails if query envelope has a non-finite coordinate
+    pub fn try_knn<Q>(&self, query: &Q, limit: usize,
+                      fn_dist_score: impl Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>,
+                      fn_predicate: impl Fn(&T, KObj<T::Envelope>) -> KnnControl) -> Result<Vec<(&T, Scalar<T>)>>
+        where Q: RTreeObject<Envelope=T::Envelope> {
+        if !geom::is_finite(&query.envelope()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        Ok(self.knn(query, limit, fn_dist_score, fn_predicate))
+    }
+
+    ///k nearest items within `max_dist` as `knn_within`, fails if query
+    ///envelope has a non-finite coordinate or `max_dist` is NaN
+    pub fn try_knn_within<Q>(&self, query: &Q, limit: usize, max_dist: Scalar<T>,
+                             fn_dist_score: impl Fn(&Q, Option<&T>, KObj<T::Envelope>) -> Scalar<T>) -> Result<Vec<(&T, Scalar<T>)>>
+        where Q: RTreeObject<Envelope=T::Envelope> {
+        if !geom::is_finite(&query.envelope()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        if geom::is_nan(max_dist) {
+            return Err(Error::InvalidDistance);
+        }
+        Ok(self.knn_within(query, limit, max_dist, fn_dist_score))
+    }
+
+    ///Minimum distance from query to items in tree as `knn_min_dist`, fails on
+    ///a query with a non-finite coordinate, a NaN `mindist` or an empty tree
+    pub fn try_knn_min_dist<Q>(&self, query: &Q,
+                               fn_dist_score: impl Fn(&Q, &T) -> Scalar<T>,
+                               fn_predicate: impl Fn(&T, KObj<T::Envelope>, Scalar<T>) -> KnnControl,
+                               mindist: Scalar<T>) -> Result<Scalar<T>>
+        where Q: RTreeObject<Envelope=T::Envelope> {
+        if !geom::is_finite(&query.envelope()) {
+            return Err(Error::NonFiniteCoordinate);
+        }
+        if geom::is_nan(mindist) {
+            return Err(Error::InvalidDistance);
+        }
         if self.is_empty() {
-            return std::f64::NAN;
+            return Err(Error::EmptyIndex);
         }
+        Ok(self.knn_min_dist(query, fn_dist_score, fn_predicate, mindist))
+    }
 
+    ///Minimum distance from query to items in tree,
+    ///query can be a point or any other object sharing the tree envelope.
+    ///`fn_predicate` is called with each candidate item, its `KObj` and the
+    ///current minimum distance, in order of distance.
+    ///Nodes are pruned by their euclidean envelope distance to query,
+    ///`fn_dist_score` must not be less than this distance.
+    ///Returns `mindist` if tree is empty (a NaN result has no counterpart for
+    ///integer scalars), see `try_knn_min_dist` to fail on an empty tree instead.
+    pub fn knn_min_dist<Q>(&self, query: &Q,
+                           fn_dist_score: impl Fn(&Q, &T) -> Scalar<T>,
+                           fn_predicate: impl Fn(&T, KObj<T::Envelope>, Scalar<T>) -> KnnControl,
+                           mindist: Scalar<T>) -> Scalar<T>
+        where Q: RTreeObject<Envelope=T::Envelope> {
+        self.knn_min_dist_until(query, fn_dist_score, fn_predicate, mindist, None)
+    }
+
+    ///Minimum distance from query to items in tree as `knn_min_dist`,
+    ///the traversal stops once the distance is within `threshold`
+    pub(crate) fn knn_min_dist_until<Q>(&self, query: &Q,
+                                        fn_dist_score: impl Fn(&Q, &T) -> Scalar<T>,
+                                        fn_predicate: impl Fn(&T, KObj<T::Envelope>, Scalar<T>) -> KnnControl,
+                                        mut mindist: Scalar<T>, threshold: Option<Scalar<T>>) -> Scalar<T>
+        where Q: RTreeObject<Envelope=T::Envelope> {
+        if self.is_empty() {
+            return mindist;
+        }
 
-        let query_box = RTree::<T>::env_mbr(&query.envelope());
+        let query_box = query.envelope();
         let mut parents = vec![Some(self.index.root())];
         let mut nd = parents[0];
         let mut stop: bool = false;
         let mut queue = BinaryHeap::new();
         let null_idx = Self::max_sentinel();
+        let mut leaves = vec![];
 
         while !stop && nd.is_some() {
             for child in nd.unwrap().children().iter() {
-                let child_box = RTree::<T>::env_mbr(&child.envelope());
-                let box_dist = child_box.distance(&query_box);
-                if box_dist < mindist {
+                let child_box = child.envelope();
+                if geom::envelope_within_distance(&child_box, &query_box, mindist) {
                     let mut o = KObj {
-                        distance: 0f64,
+                        distance: Zero::zero(),
                         is_item: child.is_leaf(),
                         mbr: child_box,
                         node: null_idx,
@@ -248,6 +466,8 @@ impl<T> RTree<T> where T: RTreeObject + Clone {
                     match child {
                         RTreeNode::Leaf(ref item) => {
                             o.distance = fn_dist_score(query, item);
+                            o.node = leaves.len();
+                            leaves.push(item);
                             if o.distance < mindist {
                                 mindist = o.distance
                             }
@@ -260,10 +480,13 @@ impl<T> RTree<T> where T: RTreeObject + Clone {
                     queue.push(o)
                 }
             }
+            if threshold.map_or(false, |t| mindist <= t) {
+                break;
+            }
 
             while !queue.is_empty() && queue.peek().unwrap().is_item {
                 let candidate = queue.pop().unwrap();
-                stop = fn_predicate(candidate, mindist);
+                stop = fn_predicate(leaves[candidate.node], candidate, mindist).is_stop();
                 if stop {
                     break;
                 }
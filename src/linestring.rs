@@ -0,0 +1,317 @@
+use std::cmp::Ordering;
+use math_util::num::{Zero, Float};
+use rstar::{RTreeObject, PointDistance, Point, Envelope, AABB};
+use crate::{RTree, KObj, KnnControl, seg_seg_distance};
+use crate::geom;
+
+///
+///Envelope of linestring vertices `i..=j`
+///
+#[derive(Clone, Debug, PartialEq)]
+pub struct MonoMBR<P> where P: Point {
+    mbr: AABB<P>,
+    pub i: usize,
+    pub j: usize,
+}
+
+impl<P> MonoMBR<P> where P: Point {
+    ///Envelope of corners `a` and `b` spanning vertices `i..=j`
+    pub fn new(a: P, b: P, i: usize, j: usize) -> MonoMBR<P> {
+        MonoMBR { mbr: AABB::from_corners(a, b), i, j }
+    }
+
+    ///Envelope of vertices `i..=j` of coords
+    pub fn from_range(coords: &[P], i: usize, j: usize) -> MonoMBR<P> {
+        MonoMBR { mbr: AABB::from_points(&coords[i..=j]), i, j }
+    }
+}
+
+impl<P> RTreeObject for MonoMBR<P> where P: Point {
+    type Envelope = AABB<P>;
+    fn envelope(&self) -> Self::Envelope {
+        self.mbr
+    }
+}
+
+impl<P> PointDistance for MonoMBR<P> where P: Point {
+    fn distance_2(&self, pt: &P) -> P::Scalar {
+        self.mbr.distance_2(pt)
+    }
+}
+
+///Envelope of each segment of coords, a single vertex is a degenerate segment
+pub fn segment_bounds<P>(coords: &[P]) -> Vec<MonoMBR<P>> where P: Point {
+    match coords.len() {
+        0 => vec![],
+        1 => vec![MonoMBR::new(coords[0], coords[0], 0, 0)],
+        _ => coords.windows(2).enumerate()
+            .map(|(i, w)| MonoMBR::new(w[0], w[1], i, i + 1))
+            .collect(),
+    }
+}
+
+///Split coords into chains of segments monotone along every axis
+pub fn monotone_chains<P>(coords: &[P]) -> Vec<MonoMBR<P>> where P: Point {
+    if coords.len() < 2 {
+        return segment_bounds(coords);
+    }
+    let mut chains = vec![];
+    let mut i = 0;
+    let mut signs = vec![Ordering::Equal; P::DIMENSIONS];
+    for (k, w) in coords.windows(2).enumerate() {
+        let step = (0..P::DIMENSIONS).map(|d| {
+            w[1].nth(d).partial_cmp(&w[0].nth(d)).unwrap_or(Ordering::Equal)
+        }).collect::<Vec<_>>();
+        let turns = signs.iter().zip(step.iter()).any(|(s, t)| {
+            *s != Ordering::Equal && *t != Ordering::Equal && s != t
+        });
+        if turns {
+            chains.push(MonoMBR::from_range(coords, i, k));
+            i = k;
+            signs = step;
+        } else {
+            for (s, t) in signs.iter_mut().zip(step) {
+                if *s == Ordering::Equal {
+                    *s = t;
+                }
+            }
+        }
+    }
+    chains.push(MonoMBR::from_range(coords, i, coords.len() - 1));
+    chains
+}
+
+///Index of linestring segments
+pub fn segment_db<P>(coords: &[P]) -> RTree<MonoMBR<P>> where P: Point {
+    RTree::load(segment_bounds(coords))
+}
+
+///Index of linestring monotone chains
+pub fn chain_db<P>(coords: &[P]) -> RTree<MonoMBR<P>> where P: Point {
+    RTree::load(monotone_chains(coords))
+}
+
+///Minimum distance between segments of vertex ranges `oa` of `a` and `ob` of `b`
+fn range_distance<P>(a: &[P], oa: &MonoMBR<P>, b: &[P], ob: &MonoMBR<P>) -> P::Scalar
+    where P: Point, P::Scalar: Float {
+    let mut dist = <P::Scalar as Float>::infinity();
+    for k in oa.i..oa.j.max(oa.i + 1) {
+        let (sa, sb) = (a[k], a[(k + 1).min(oa.j)]);
+        for m in ob.i..ob.j.max(ob.i + 1) {
+            dist = Float::min(dist, seg_seg_distance(sa, sb, b[m], b[(m + 1).min(ob.j)]));
+        }
+    }
+    dist
+}
+
+///Minimum distance between linestrings: segments of the shorter are queried
+///against an index of monotone chains of the longer. Infinite if either is empty.
+pub fn min_distance<P>(a: &[P], b: &[P]) -> P::Scalar where P: Point, P::Scalar: Float {
+    let (a, b) = if a.len() > b.len() { (b, a) } else { (a, b) };
+    let db = chain_db(b);
+    let zero = P::Scalar::zero();
+    let mut dist = <P::Scalar as Float>::infinity();
+    for query in segment_bounds(a).iter() {
+        if dist == zero {
+            break;
+        }
+        dist = db.knn_min_dist(
+            query,
+            |q, item| range_distance(a, q, b, item),
+            |_, o, d| if o.distance > d || d == zero { KnnControl::Stop } else { KnnControl::Skip },
+            dist,
+        );
+    }
+    dist
+}
+
+///
+///Index of a linestring by monotone chains, queries report original vertex indices
+///
+#[derive(Clone, Debug)]
+pub struct MonoChainIndex<P> where P: Point {
+    coords: Vec<P>,
+    chains: RTree<MonoMBR<P>>,
+}
+
+impl<P> MonoChainIndex<P> where P: Point {
+    pub fn new(coords: Vec<P>) -> Self {
+        let chains = chain_db(&coords);
+        MonoChainIndex { coords, chains }
+    }
+
+    ///Vertices of indexed linestring
+    pub fn coords(&self) -> &[P] {
+        &self.coords
+    }
+
+    ///Index of monotone chains
+    pub fn chains(&self) -> &RTree<MonoMBR<P>> {
+        &self.chains
+    }
+
+    ///Segments `(k, k + 1)` intersecting segment ab in order of vertex index,
+    ///a single vertex linestring is the degenerate segment `(0, 0)`
+    pub fn intersecting_segments(&self, a: &P, b: &P) -> Vec<(usize, usize)> {
+        let mut segments = vec![];
+        for chain in self.chains.search_segment(a, b) {
+            for k in chain.i..chain.j.max(chain.i + 1) {
+                let (m, n) = (k, (k + 1).min(chain.j));
+                if geom::segments_intersect(&self.coords[m], &self.coords[n], a, b) {
+                    segments.push((m, n));
+                }
+            }
+        }
+        segments.sort_unstable();
+        segments
+    }
+}
+
+impl<P> MonoChainIndex<P> where P: Point, P::Scalar: Float {
+    ///Nearest vertex to pt as (vertex index, distance), None if empty
+    pub fn nearest_vertex(&self, pt: &P) -> Option<(usize, P::Scalar)> {
+        self.chains.knn_iter(pt, |q, item, o: KObj<AABB<P>>| match item {
+            Some(chain) => self.nearest_in_range(q, chain).1,
+            None => Float::sqrt(o.mbr.distance_2(q)),
+        }).next().map(|(chain, _)| self.nearest_in_range(pt, chain))
+    }
+
+    ///Hausdorff distance to other over vertices of both linestrings: the largest
+    ///distance from a vertex of either linestring to the other. Infinite if either is empty.
+    pub fn hausdorff_distance(&self, other: &Self) -> P::Scalar {
+        if self.coords.is_empty() || other.coords.is_empty() {
+            return Float::infinity();
+        }
+        let hmax = self.directed_hausdorff(other, Zero::zero());
+        other.directed_hausdorff(self, hmax)
+    }
+
+    ///Largest distance from vertices of self to other and `hmax`, the search
+    ///of a vertex stops early once it is within `hmax` of other
+    fn directed_hausdorff(&self, other: &Self, mut hmax: P::Scalar) -> P::Scalar {
+        for (k, v) in self.coords.iter().enumerate() {
+            let query = MonoMBR::new(*v, *v, k, k);
+            let dist = other.chains.knn_min_dist_until(
+                &query,
+                |q, item| range_distance(&self.coords, q, &other.coords, item),
+                |_, o, d| if o.distance > d { KnnControl::Stop } else { KnnControl::Skip },
+                Float::infinity(),
+                Some(hmax),
+            );
+            hmax = Float::max(hmax, dist);
+        }
+        hmax
+    }
+
+    ///Discrete Fréchet distance to other over vertices of both linestrings.
+    ///Bounded below by the end point distances and the Hausdorff distance, and
+    ///above by a greedy coupling; returns early when the bounds meet. Otherwise
+    ///only vertex pairs within the upper bound, found by the chain index of other,
+    ///are coupled. Infinite if either is empty.
+    pub fn frechet_distance(&self, other: &Self) -> P::Scalar {
+        let (a, b) = (&self.coords, &other.coords);
+        if a.is_empty() || b.is_empty() {
+            return Float::infinity();
+        }
+        let (n, m) = (a.len() - 1, b.len() - 1);
+        let lower = Float::max(
+            Float::max(vertex_distance(&a[0], &b[0]), vertex_distance(&a[n], &b[m])),
+            self.hausdorff_distance(other),
+        );
+        let upper = greedy_coupling(a, b);
+        if upper <= lower {
+            return upper;
+        }
+
+        let inf = <P::Scalar as Float>::infinity();
+        let (mut prev, mut curr) = (vec![inf; b.len()], vec![inf; b.len()]);
+        let (mut prev_cells, mut curr_cells) = (vec![], vec![]);
+        for (i, u) in a.iter().enumerate() {
+            for &j in curr_cells.iter() {
+                curr[j] = inf;
+            }
+            curr_cells.clear();
+            for j in other.vertices_near(u, upper) {
+                let d = vertex_distance(u, &b[j]);
+                if d > upper {
+                    continue;
+                }
+                let reach = match (i, j) {
+                    (0, 0) => Zero::zero(),
+                    (0, _) => curr[j - 1],
+                    (_, 0) => prev[0],
+                    _ => Float::min(Float::min(prev[j], prev[j - 1]), curr[j - 1]),
+                };
+                if reach <= upper {
+                    curr[j] = Float::max(reach, d);
+                    curr_cells.push(j);
+                }
+            }
+            std::mem::swap(&mut prev, &mut curr);
+            std::mem::swap(&mut prev_cells, &mut curr_cells);
+        }
+        Float::min(prev[m], upper)
+    }
+
+    ///Vertex indices in order of chains whose envelope is within `dist` of pt
+    fn vertices_near(&self, pt: &P, dist: P::Scalar) -> Vec<usize> {
+        let lo = P::generate(|i| pt.nth(i) - dist);
+        let hi = P::generate(|i| pt.nth(i) + dist);
+        let mut vertices = vec![];
+        for chain in self.chains.search(&AABB::from_corners(lo, hi)) {
+            vertices.extend(chain.i..=chain.j);
+        }
+        vertices.sort_unstable();
+        vertices.dedup();
+        vertices
+    }
+
+    ///Nearest vertex to pt within vertex range of chain
+    fn nearest_in_range(&self, pt: &P, chain: &MonoMBR<P>) -> (usize, P::Scalar) {
+        (chain.i..=chain.j)
+            .map(|k| (k, vertex_distance(&self.coords[k], pt)))
+            .fold((chain.i, Float::infinity()), |a, b| if b.1 < a.1 { b } else { a })
+    }
+}
+
+///Euclidean distance between vertices
+fn vertex_distance<P>(a: &P, b: &P) -> P::Scalar where P: Point, P::Scalar: Float {
+    let sum = (0..P::DIMENSIONS).fold(P::Scalar::zero(), |sum, i| {
+        let d = a.nth(i) - b.nth(i);
+        sum + d * d
+    });
+    Float::sqrt(sum)
+}
+
+///Hausdorff distance between linestrings over their vertices, see `MonoChainIndex::hausdorff_distance`
+pub fn hausdorff_distance<P>(a: &[P], b: &[P]) -> P::Scalar where P: Point, P::Scalar: Float {
+    MonoChainIndex::new(a.to_vec()).hausdorff_distance(&MonoChainIndex::new(b.to_vec()))
+}
+
+///Discrete Fréchet distance between linestrings over their vertices,
+///see `MonoChainIndex::frechet_distance`
+pub fn frechet_distance<P>(a: &[P], b: &[P]) -> P::Scalar where P: Point, P::Scalar: Float {
+    MonoChainIndex::new(a.to_vec()).frechet_distance(&MonoChainIndex::new(b.to_vec()))
+}
+
+///Largest vertex distance along a greedy coupling of a and b (each step takes
+///the closest next pair), an upper bound of their discrete Fréchet distance
+fn greedy_coupling<P>(a: &[P], b: &[P]) -> P::Scalar where P: Point, P::Scalar: Float {
+    let (n, m) = (a.len() - 1, b.len() - 1);
+    let (mut i, mut j) = (0, 0);
+    let mut dist = vertex_distance(&a[0], &b[0]);
+    while i < n || j < m {
+        let (p, q, d) = [(i + 1, j + 1), (i + 1, j), (i, j + 1)].iter()
+            .filter(|&&(p, q)| p <= n && q <= m)
+            .map(|&(p, q)| (p, q, vertex_distance(&a[p], &b[q])))
+            .fold(None, |best: Option<(usize, usize, P::Scalar)>, o| match best {
+                Some(x) if x.2 <= o.2 => Some(x),
+                _ => Some(o),
+            })
+            .unwrap();
+        i = p;
+        j = q;
+        dist = Float::max(dist, d);
+    }
+    dist
+}
@@ -0,0 +1,48 @@
+use math_util::num::Float;
+use rstar::{RTreeObject, Envelope};
+use crate::{RTree, KObj, Scalar};
+use crate::geom;
+
+impl<T> RTree<T> where T: RTreeObject + Clone, Scalar<T>: Float {
+    ///Lazy iterator of items hit by ray `origin + t * direction` for t in [0, max_t],
+    ///in order of hit parameter t. Nodes are traversed best-first by the entry parameter
+    ///of the ray into their envelope; `fn_hit` refines the entry parameter of an item
+    ///envelope to the exact hit parameter (not less than the entry), None on a miss.
+    pub fn raycast_iter(
+        &self, origin: &<T::Envelope as Envelope>::Point,
+        direction: &<T::Envelope as Envelope>::Point, max_t: Scalar<T>,
+        fn_hit: impl Fn(&T, Scalar<T>) -> Option<Scalar<T>>,
+    ) -> impl Iterator<Item=(&T, Scalar<T>)> {
+        let (origin, direction) = (*origin, *direction);
+        let fn_score = move |_: &(), item: Option<&T>, o: KObj<T::Envelope>| {
+            let miss = Float::infinity();
+            match geom::ray_envelope_entry(&origin, &direction, &o.mbr, max_t) {
+                None => miss,
+                Some(t) => match item {
+                    None => t,
+                    Some(item) => fn_hit(item, t).filter(|&h| h <= max_t).unwrap_or(miss),
+                }
+            }
+        };
+        self.knn_iter_pruned(&(), fn_score, move |o| o.distance > max_t)
+            .take_while(move |&(_, t)| t <= max_t)
+    }
+
+    ///Items hit by ray in order of hit parameter, see `raycast_iter`
+    pub fn raycast(
+        &self, origin: &<T::Envelope as Envelope>::Point,
+        direction: &<T::Envelope as Envelope>::Point, max_t: Scalar<T>,
+        fn_hit: impl Fn(&T, Scalar<T>) -> Option<Scalar<T>>,
+    ) -> Vec<(&T, Scalar<T>)> {
+        self.raycast_iter(origin, direction, max_t, fn_hit).collect()
+    }
+
+    ///First item hit by ray, see `raycast_iter`
+    pub fn raycast_first(
+        &self, origin: &<T::Envelope as Envelope>::Point,
+        direction: &<T::Envelope as Envelope>::Point, max_t: Scalar<T>,
+        fn_hit: impl Fn(&T, Scalar<T>) -> Option<Scalar<T>>,
+    ) -> Option<(&T, Scalar<T>)> {
+        self.raycast_iter(origin, direction, max_t, fn_hit).next()
+    }
+}
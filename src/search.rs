@@ -0,0 +1,201 @@
+use rstar::{RTreeObject, RTreeNode, Envelope, ParentNode};
+use math_util::num::Float;
+use crate::{RTree, Scalar};
+use crate::geom::{self, Relation};
+
+impl<T> RTree<T> where T: RTreeObject {
+    ///Items accepted by `fn_item` in parent nodes accepted by `fn_node`
+    pub(crate) fn select(&self,
+                         fn_node: impl Fn(&T::Envelope) -> bool,
+                         fn_item: impl Fn(&T) -> bool) -> Vec<&T> {
+        let mut results = vec![];
+        let mut stack = vec![self.index.root()];
+        while let Some(nd) = stack.pop() {
+            for child in nd.children().iter() {
+                match child {
+                    RTreeNode::Leaf(ref item) => {
+                        if fn_item(item) {
+                            results.push(item);
+                        }
+                    }
+                    RTreeNode::Parent(ref p) => {
+                        if fn_node(&child.envelope()) {
+                            stack.push(p);
+                        }
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    ///All items under node
+    pub(crate) fn collect_items<'a>(node: &'a RTreeNode<T>, results: &mut Vec<&'a T>) {
+        let mut stack = vec![node];
+        while let Some(nd) = stack.pop() {
+            match nd {
+                RTreeNode::Leaf(ref item) => results.push(item),
+                RTreeNode::Parent(ref p) => stack.extend(p.children().iter()),
+            }
+        }
+    }
+
+    ///First item accepted by `fn_item` in parent nodes accepted by `fn_node`
+    pub(crate) fn find(&self,
+                       fn_node: impl Fn(&T::Envelope) -> bool,
+                       fn_item: impl Fn(&T) -> bool) -> Option<&T> {
+        let mut stack: Vec<&ParentNode<T>> = vec![self.index.root()];
+        while let Some(nd) = stack.pop() {
+            for child in nd.children().iter() {
+                match child {
+                    RTreeNode::Leaf(ref item) => {
+                        if fn_item(item) {
+                            return Some(item);
+                        }
+                    }
+                    RTreeNode::Parent(ref p) => {
+                        if fn_node(&child.envelope()) {
+                            stack.push(p);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    ///Items with envelope fully inside envelope
+    pub fn search_contained_in(&self, envelope: &T::Envelope) -> Vec<&T> {
+        self.select(
+            |node| node.intersects(envelope),
+            |item| envelope.contains_envelope(&item.envelope()),
+        )
+    }
+
+    ///Items with envelope covering envelope
+    pub fn search_containing(&self, envelope: &T::Envelope) -> Vec<&T> {
+        self.select(
+            |node| node.contains_envelope(envelope),
+            |item| item.envelope().contains_envelope(envelope),
+        )
+    }
+
+    ///Items whose envelope covers point
+    pub fn locate_all_at_point(&self, pt: &<T::Envelope as Envelope>::Point) -> Vec<&T> {
+        self.locate_all_at_point_with(pt, |_, _| true)
+    }
+
+    ///Items whose envelope covers point and accepted by the exact geometry test `fn_refine`
+    pub fn locate_all_at_point_with(
+        &self, pt: &<T::Envelope as Envelope>::Point,
+        fn_refine: impl Fn(&T, &<T::Envelope as Envelope>::Point) -> bool,
+    ) -> Vec<&T> {
+        self.select(
+            |node| node.contains_point(pt),
+            |item| item.envelope().contains_point(pt) && fn_refine(item, pt),
+        )
+    }
+
+    ///An item whose envelope covers point
+    pub fn locate_at_point(&self, pt: &<T::Envelope as Envelope>::Point) -> Option<&T> {
+        self.locate_at_point_with(pt, |_, _| true)
+    }
+
+    ///An item whose envelope covers point and accepted by the exact geometry test `fn_refine`
+    pub fn locate_at_point_with(
+        &self, pt: &<T::Envelope as Envelope>::Point,
+        fn_refine: impl Fn(&T, &<T::Envelope as Envelope>::Point) -> bool,
+    ) -> Option<&T> {
+        self.find(
+            |node| node.contains_point(pt),
+            |item| item.envelope().contains_point(pt) && fn_refine(item, pt),
+        )
+    }
+
+    ///Items whose envelope intersects polygon (open or closed ring)
+    pub fn search_polygon(&self, polygon: &[<T::Envelope as Envelope>::Point]) -> Vec<&T> {
+        self.search_polygon_with(polygon, |_, _| true)
+    }
+
+    ///Items intersecting polygon (open or closed ring): nodes disjoint from polygon
+    ///are pruned, nodes within polygon are accepted without further tests,
+    ///other items are accepted by the exact intersection test `fn_refine`
+    pub fn search_polygon_with(
+        &self, polygon: &[<T::Envelope as Envelope>::Point],
+        fn_refine: impl Fn(&T, &[<T::Envelope as Envelope>::Point]) -> bool,
+    ) -> Vec<&T> {
+        let mut results = vec![];
+        let mut stack = vec![self.index.root()];
+        while let Some(nd) = stack.pop() {
+            for child in nd.children().iter() {
+                match geom::envelope_polygon_relation(&child.envelope(), polygon) {
+                    Relation::Disjoint => {}
+                    Relation::Within => Self::collect_items(child, &mut results),
+                    Relation::Intersects => match child {
+                        RTreeNode::Leaf(ref item) => {
+                            if fn_refine(item, polygon) {
+                                results.push(item);
+                            }
+                        }
+                        RTreeNode::Parent(ref p) => stack.push(p),
+                    }
+                }
+            }
+        }
+        results
+    }
+
+    ///Items whose envelope intersects segment ab
+    pub fn search_segment(&self, a: &<T::Envelope as Envelope>::Point,
+                          b: &<T::Envelope as Envelope>::Point) -> Vec<&T> {
+        self.search_polyline(&[*a, *b])
+    }
+
+    ///Items whose envelope intersects polyline,
+    ///a single vertex polyline is a zero-length segment
+    pub fn search_polyline(&self, line: &[<T::Envelope as Envelope>::Point]) -> Vec<&T> {
+        self.search_polyline_with(line, |_, _| true)
+    }
+
+    ///Items whose envelope intersects polyline and accepted by
+    ///the exact intersection test `fn_refine`
+    pub fn search_polyline_with(
+        &self, line: &[<T::Envelope as Envelope>::Point],
+        fn_refine: impl Fn(&T, &[<T::Envelope as Envelope>::Point]) -> bool,
+    ) -> Vec<&T> {
+        let intersects = |envelope: &T::Envelope| {
+            geom::polyline_segments(line).any(|(a, b)| geom::segment_intersects_envelope(a, b, envelope))
+        };
+        self.select(
+            |node| intersects(node),
+            |item| intersects(&item.envelope()) && fn_refine(item, line),
+        )
+    }
+}
+
+impl<T> RTree<T> where T: RTreeObject, Scalar<T>: Float {
+    ///Minimum distance from envelope to polyline
+    fn polyline_envelope_distance(line: &[<T::Envelope as Envelope>::Point], envelope: &T::Envelope) -> Scalar<T> {
+        geom::polyline_segments(line)
+            .map(|(a, b)| geom::segment_envelope_distance(a, b, envelope))
+            .fold(Float::infinity(), Float::min)
+    }
+
+    ///Items whose envelope is within distance of polyline,
+    ///a single vertex polyline is a zero-length segment
+    pub fn search_corridor(&self, line: &[<T::Envelope as Envelope>::Point], dist: Scalar<T>) -> Vec<&T> {
+        self.search_corridor_with(line, dist, |_, _| true)
+    }
+
+    ///Items whose envelope is within distance of polyline and accepted by
+    ///the exact distance test `fn_refine`
+    pub fn search_corridor_with(
+        &self, line: &[<T::Envelope as Envelope>::Point], dist: Scalar<T>,
+        fn_refine: impl Fn(&T, &[<T::Envelope as Envelope>::Point]) -> bool,
+    ) -> Vec<&T> {
+        self.select(
+            |node| Self::polyline_envelope_distance(line, node) <= dist,
+            |item| Self::polyline_envelope_distance(line, &item.envelope()) <= dist && fn_refine(item, line),
+        )
+    }
+}
@@ -1,9 +1,18 @@
 mod common;
 
-use common::{MonoMBR, Pt};
+use common::{MonoMBR, Pt, Wkt};
 use super::{*};
-use crate::tests::common::{min_dist_brute_force, Pts, knn_min_linear_distance};
+use crate::tests::common::{min_dist_brute_force, frechet_brute_force, Pts};
+use crate::linestring::{self, segment_db};
 use math_util::{Numeric, NumCast, num};
+use bbox_2d::MBR;
+
+type Env = <MBR as RTreeObject>::Envelope;
+
+fn env_mbr(envelope: &Env) -> MBR {
+    let (ll, ur) = (envelope.lower_left(), envelope.upper_right());
+    MBR::new(ll.nth(0), ll.nth(1), ur.nth(0), ur.nth(1))
+}
 
 fn array_as_mbr<T>(o: [T; 4]) -> MBR where T: Numeric + NumCast + Copy {
     MBR::new_from_array([
@@ -14,12 +23,12 @@ fn array_as_mbr<T>(o: [T; 4]) -> MBR where T: Numeric + NumCast + Copy {
     ])
 }
 
-fn score_fn(query: &MBR, _: Option<&MBR>, boxer: KObj) -> f64 {
-    query.distance(&boxer.mbr)
+fn score_fn(query: &MBR, _: Option<&MBR>, boxer: KObj<Env>) -> f64 {
+    query.distance(&env_mbr(&boxer.mbr))
 }
 
-fn knn_predicate(_: KObj) -> (bool, bool) {
-    (true, false)
+fn knn_predicate(_: &MBR, _: KObj<Env>) -> KnnControl {
+    KnnControl::Accept
 }
 
 fn init_knn() -> Vec<MBR> {
@@ -94,7 +103,18 @@ fn test_rtree() {
         None => println!("None!")
     }
     println!("tree size = {}", tree.size());
-    tree.each(|v| println!("{}", v.wkt()));
+    tree.each(|v| {
+        println!("{}", v.wkt());
+        ControlFlow::Continue(())
+    });
+    let mut count = 0;
+    tree.each(|_| {
+        count += 1;
+        ControlFlow::Break(())
+    });
+    assert_eq!(count, 1);
+    assert_eq!(tree.iter().count(), 2);
+    assert!(tree.iter().all(|v| tree.contains(v)));
     let rt = tree.rtree();
     assert_eq!(rt.size(), 2);
 
@@ -105,6 +125,28 @@ fn test_rtree() {
     assert_eq!(all_items.len(), n);
 }
 
+#[test]
+fn test_nearest_with_distance() {
+    let items = vec![
+        MonoMBR::new(Pt { x: 0., y: 0. }, Pt { x: 1., y: 1. }, 0, 3),
+        MonoMBR::new(Pt { x: 1., y: 1. }, Pt { x: 2., y: 2. }, 3, 7),
+        MonoMBR::new(Pt { x: 4., y: 2. }, Pt { x: 7.0, y: 3.0 }, 7, 11),
+    ];
+    let tree = RTree::load(items);
+    let (nn, dist) = tree.nearest_neighbor(&Pt { x: 4., y: 0. }).unwrap();
+    assert_eq!(nn, &MonoMBR::new(Pt { x: 4., y: 2. }, Pt { x: 7.0, y: 3.0 }, 7, 11));
+    assert_eq!(dist, 4.0);
+
+    let mut res = tree.locate_within_distance(Pt { x: 3., y: 2. }, 1.5);
+    res.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    assert_eq!(res.len(), 2);
+    assert_eq!(res[0].1, 1.0);
+    assert_eq!(res[1].1, 1.0);
+
+    let res = tree.locate_within_distance(Pt { x: 3., y: -1. }, 1.5);
+    assert_eq!(res.len(), 0);
+}
+
 #[test]
 fn knn_tree_knn() {
     let items = init_knn();
@@ -120,22 +162,89 @@ fn knn_tree_knn() {
     let result = result.iter()
         .map(|v| array_as_mbr(*v)).collect::<Vec<MBR>>();
     assert_eq!(result.len(), 10);
-    for n in nn {
+    for (n, dist) in nn {
         assert!(found_in(n, &result));
+        assert_eq!(dist, query.distance(n));
     }
     let nn = rt.knn(&query, 1000, score_fn, knn_predicate);
     assert_eq!(nn.len(), init_knn().len());
 }
 
+#[test]
+fn knn_tree_knn_iter() {
+    let items = init_knn();
+    let rt = RTree::load(items);
+    let query = array_as_mbr([40, 40, 40, 40]);
+    let nn = rt.knn(&query, 10, score_fn, knn_predicate);
+    let it = rt.knn_iter(&query, score_fn).take(10).collect::<Vec<_>>();
+    assert_eq!(it.len(), nn.len());
+    for (i, (item, dist)) in it.iter().enumerate() {
+        assert_eq!(item.as_array(), nn[i].0.as_array());
+        assert_eq!(*dist, nn[i].1);
+    }
+
+    let mut prev = 0f64;
+    let mut count = 0;
+    for (_, dist) in rt.knn_iter(&query, score_fn) {
+        assert!(dist >= prev);
+        prev = dist;
+        count += 1;
+    }
+    assert_eq!(count, init_knn().len());
+
+    let near = rt.knn_iter(&query, score_fn)
+        .take_while(|&(_, dist)| dist <= 5.0)
+        .count();
+    assert_eq!(near, 3);
+}
+
+#[test]
+fn knn_tree_knn_by_point_and_envelope() {
+    let items = init_knn();
+    let rt = RTree::load(items);
+    let query = array_as_mbr([40, 40, 40, 40]);
+    let nn = rt.knn(&query, 10, score_fn, knn_predicate);
+
+    let pt = query.envelope().lower_left();
+    let by_pt = rt.knn(&pt, 10, |q, _, o: KObj<Env>| {
+        env_mbr(&o.mbr).distance(&MBR::new(q.nth(0), q.nth(1), q.nth(0), q.nth(1)))
+    }, knn_predicate);
+    assert_eq!(by_pt.len(), nn.len());
+
+    let env = query.envelope();
+    let by_env = rt.knn(&env, 10, |q, _, o: KObj<Env>| {
+        env_mbr(&o.mbr).distance(&env_mbr(q))
+    }, knn_predicate);
+    assert_eq!(by_env.len(), nn.len());
+
+    for i in 0..nn.len() {
+        assert_eq!(by_pt[i].0.as_array(), nn[i].0.as_array());
+        assert_eq!(by_env[i].0.as_array(), nn[i].0.as_array());
+    }
+
+    let q = array_as_mbr([40, 40, 40, 40]);
+    let dist = rt.knn_min_dist(&pt, |_, item: &MBR| item.distance(&q), |_, _, _| KnnControl::Skip, std::f64::MAX);
+    assert_eq!(dist, nn[0].1);
+
+    //predicate sees the item it judges
+    let seen = std::cell::RefCell::new(vec![]);
+    rt.knn_min_dist(&pt, |_, item: &MBR| item.distance(&q), |item, o, _| {
+        seen.borrow_mut().push((item.distance(&q), o.distance));
+        KnnControl::Skip
+    }, std::f64::MAX);
+    assert!(!seen.borrow().is_empty());
+    assert!(seen.borrow().iter().all(|(a, b)| a == b));
+}
+
 #[test]
 fn knn_tree_custom_predicate_knn() {
-    fn create_predicate(dist: f64) -> impl Fn(KObj) -> (bool, bool) {
-        move |candidate: KObj| -> (bool, bool) {
+    fn create_predicate(dist: f64) -> impl Fn(&MBR, KObj<Env>) -> KnnControl {
+        move |_: &MBR, candidate: KObj<Env>| -> KnnControl {
             assert!(candidate.is_item);
             if candidate.distance <= dist {
-                return (true, false);
+                return KnnControl::Accept;
             }
-            return (false, true);
+            return KnnControl::Stop;
         }
     }
     let data = init_knn();
@@ -149,11 +258,616 @@ fn knn_tree_custom_predicate_knn() {
     let res = rt.knn(&query, 10, score_fn, pred_fn);
     let expected_mbrs = knn_data_as_f64(vec![[69, 78, 73, 78], [71, 87, 71, 88]]);
     assert_eq!(res.len(), 2);
-    for (i, r) in res.into_iter().enumerate() {
+    for (i, (r, _)) in res.into_iter().enumerate() {
+        assert_eq!(r.as_array(), expected_mbrs[i]);
+    }
+
+    let res = rt.knn_within(&query, 10, 6.0, score_fn);
+    assert_eq!(res.len(), 2);
+    for (i, (r, _)) in res.into_iter().enumerate() {
         assert_eq!(r.as_array(), expected_mbrs[i]);
     }
+    let res = rt.knn_within(&query, 1, 6.0, score_fn);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].0.as_array(), expected_mbrs[0]);
+    assert_eq!(rt.knn_within(&query, 0, 1.0, score_fn).len(), 0);
+    assert_eq!(rt.knn_within(&query, 0, 1000.0, score_fn).len(), init_knn().len());
+}
+
+
+#[test]
+fn knn_tree_knn_control() {
+    let rt = RTree::load(init_knn());
+    let query = array_as_mbr([40, 40, 40, 40]);
+
+    let res = rt.knn(&query, 0, score_fn, |_, _| KnnControl::AcceptAndStop);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].0.as_array(), array_as_mbr([38, 39, 39, 39]).as_array());
+
+    let res = rt.knn(&query, 0, score_fn, |_, _| KnnControl::Stop);
+    assert_eq!(res.len(), 0);
+
+    let res = rt.knn(&query, 3, score_fn, |_, o: KObj<Env>| {
+        if o.distance < 2.0 { KnnControl::Skip } else { KnnControl::Accept }
+    });
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0].0.as_array(), array_as_mbr([35, 39, 38, 40]).as_array());
+    assert_eq!(res[0].1, 2.0);
+}
+
+#[test]
+fn knn_tree_knn_pruned() {
+    let rt = RTree::load(init_knn());
+    let query = array_as_mbr([40, 40, 40, 40]);
+    let region = array_as_mbr([30, 30, 50, 50]);
+
+    let nn = rt.knn(&query, 0, score_fn, |_, o: KObj<Env>| {
+        if region.intersects(&env_mbr(&o.mbr)) { KnnControl::Accept } else { KnnControl::Skip }
+    });
+    let res = rt.knn_pruned(&query, 0, score_fn, knn_predicate, |o: KObj<Env>| {
+        !region.intersects(&env_mbr(&o.mbr))
+    });
+    let res = res.into_iter().filter(|(o, _)| region.intersects(o)).collect::<Vec<_>>();
+    assert_eq!(res.len(), nn.len());
+    for (i, (o, _)) in res.into_iter().enumerate() {
+        assert_eq!(o.as_array(), nn[i].0.as_array());
+    }
+
+    let count = rt.knn_iter_pruned(&query, score_fn, |o: KObj<Env>| o.distance > 8.0)
+        .take_while(|&(_, dist)| dist <= 8.0)
+        .count();
+    assert_eq!(count, rt.knn_iter(&query, score_fn).take_while(|&(_, dist)| dist <= 8.0).count());
+}
+
+#[test]
+fn knn_tree_knn_item_predicate() {
+    let rt = RTree::load(init_knn());
+    let query = array_as_mbr([40, 40, 40, 40]);
+    let is_point = |o: &MBR| o.as_array()[0] == o.as_array()[2] && o.as_array()[1] == o.as_array()[3];
+
+    let res = rt.knn(&query, 3, score_fn, |item: &MBR, _| {
+        if is_point(item) { KnnControl::Accept } else { KnnControl::Skip }
+    });
+    assert_eq!(res.len(), 3);
+    assert_eq!(res[0].0.as_array(), array_as_mbr([38, 60, 38, 60]).as_array());
+    for (o, _) in res {
+        assert!(is_point(o));
+    }
+}
+
+#[test]
+fn knn_tree_integer_scalar() {
+    let pts: Vec<[i64; 2]> = vec![[0, 0], [3, 4], [10, 10], [-5, 2], [7, -1], [2, 2], [100, 100]];
+    let rt = RTree::load(pts);
+    let query = [1i64, 1];
+    let score = |q: &[i64; 2], _: Option<&[i64; 2]>, o: KObj<AABB<[i64; 2]>>| o.mbr.distance_2(q);
+
+    let res = rt.knn(&query, 3, score, RTree::<[i64; 2]>::knn_predicate);
+    assert_eq!(res.iter().map(|o| o.1).collect::<Vec<_>>(), vec![2, 2, 13]);
+    assert_eq!(*res[2].0, [3, 4]);
+
+    let res = rt.knn_within(&query, 0, 40, score);
+    assert_eq!(res.len(), 5);
+
+    let dist = rt.knn_min_dist(&[101i64, 101], |q, item| {
+        (q[0] - item[0]).abs().max((q[1] - item[1]).abs())
+    }, |_, _, _| KnnControl::Skip, std::i64::MAX);
+    assert_eq!(dist, 1);
+}
+
+#[test]
+fn test_fallible_api() {
+    let rt: RTree<MonoMBR> = RTree::new();
+    let pt = Pt { x: 1., y: 1. };
+    assert_eq!(rt.try_nearest_neighbor(&pt).err(), Some(Error::EmptyIndex));
+    assert_eq!(
+        rt.try_knn_min_dist(&pt, |_, o: &MonoMBR| o.distance_2(&pt), |_, _, _| KnnControl::Skip, std::f64::MAX),
+        Err(Error::EmptyIndex)
+    );
+
+    let items = vec![[0., 0.], [std::f64::NAN, 1.]];
+    assert_eq!(RTree::try_load(items).err(), Some(Error::NonFiniteCoordinate));
+
+    let mut rt = RTree::try_load(vec![[0., 0.], [1., 1.]]).unwrap();
+    assert_eq!(rt.try_insert([std::f64::INFINITY, 1.]), Err(Error::NonFiniteCoordinate));
+    assert_eq!(rt.try_insert([2., 2.]), Ok(()));
+    assert_eq!(rt.size(), 3);
+
+    assert_eq!(rt.try_nearest_neighbor(&[std::f64::NAN, 0.]).err(), Some(Error::NonFiniteCoordinate));
+    let (nn, dist) = rt.try_nearest_neighbor(&[3., 2.]).unwrap();
+    assert_eq!(*nn, [2., 2.]);
+    assert_eq!(dist, 1.0);
+    assert_eq!(format!("{}", Error::EmptyIndex), "empty index");
+
+    //empty tree: knn_min_dist returns the caller's mindist
+    let empty: RTree<[f64; 2]> = RTree::new();
+    let sqr_dist = |q: &[f64; 2], o: &[f64; 2]| (q[0] - o[0]).powi(2) + (q[1] - o[1]).powi(2);
+    assert_eq!(empty.knn_min_dist(&[0., 0.], sqr_dist, |_, _, _| KnnControl::Skip, 42.0), 42.0);
+    assert_eq!(empty.try_knn_min_dist(&[0., 0.], sqr_dist, |_, _, _| KnnControl::Skip, 42.0), Err(Error::EmptyIndex));
+    //bad input is reported before an empty index, coordinates before distances
+    let nan_pt = [std::f64::NAN, 0.];
+    assert_eq!(empty.try_nearest_neighbor(&nan_pt).err(), Some(Error::NonFiniteCoordinate));
+    assert_eq!(empty.try_knn_min_dist(&nan_pt, sqr_dist, |_, _, _| KnnControl::Skip, 42.0), Err(Error::NonFiniteCoordinate));
+    assert_eq!(empty.try_knn_min_dist(&[0., 0.], sqr_dist, |_, _, _| KnnControl::Skip, std::f64::NAN), Err(Error::InvalidDistance));
+    assert_eq!(empty.try_knn_min_dist(&nan_pt, sqr_dist, |_, _, _| KnnControl::Skip, std::f64::NAN), Err(Error::NonFiniteCoordinate));
+    assert_eq!(format!("{}", Error::InvalidDistance), "invalid distance");
+
+    let nan = [std::f64::NAN, 0.];
+    let score = |q: &[f64; 2], _: Option<&[f64; 2]>, o: KObj<AABB<[f64; 2]>>| o.mbr.distance_2(q);
+    assert_eq!(rt.try_knn(&nan, 1, score, |_, _| KnnControl::Accept).err(), Some(Error::NonFiniteCoordinate));
+    assert_eq!(rt.try_knn_within(&[0., 0.], 0, std::f64::NAN, score).err(), Some(Error::InvalidDistance));
+    assert_eq!(rt.try_knn_within(&nan, 0, std::f64::NAN, score).err(), Some(Error::NonFiniteCoordinate));
+    assert!(rt.try_knn_iter(&nan, score).is_err());
+    assert_eq!(rt.try_knn(&[3., 2.], 1, score, |_, _| KnnControl::Accept).unwrap(), vec![(&[2., 2.], 1.0)]);
+    assert_eq!(rt.try_knn_within(&[0., 0.], 0, 2.0, score).unwrap().len(), 2);
+    assert_eq!(rt.try_knn_iter(&[0., 0.], score).unwrap().count(), 3);
+
+    let bad = AABB::from_point(nan);
+    assert_eq!(rt.try_search(&bad).err(), Some(Error::NonFiniteCoordinate));
+    assert!(rt.try_search_iter(&bad).is_err());
+    assert_eq!(rt.try_search(&AABB::from_corners([0., 0.], [1., 1.])).unwrap().len(), 2);
+    assert!(rt.try_within_distance_iter(nan, 1.0).is_err());
+    assert_eq!(rt.try_locate_within_distance([0., 0.], std::f64::NAN).err(), Some(Error::InvalidDistance));
+    assert_eq!(rt.try_locate_within_distance(nan, std::f64::NAN).err(), Some(Error::NonFiniteCoordinate));
+    assert_eq!(rt.try_locate_within_distance([0., 0.], 2.0).unwrap().len(), 2);
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Tagged {
+    pt: [f64; 2],
+    tag: usize,
+}
+
+impl RTreeObject for Tagged {
+    type Envelope = AABB<[f64; 2]>;
+    fn envelope(&self) -> Self::Envelope {
+        AABB::from_point(self.pt)
+    }
+}
+
+#[test]
+fn test_drain_and_mutate() {
+    let items = (0..20).map(|i| Tagged { pt: [i as f64, 0.], tag: 0 }).collect();
+    let mut rt = RTree::load(items);
+    for o in rt.iter_mut() {
+        o.tag = 1;
+    }
+    assert_eq!(rt.iter().filter(|o| o.tag == 1).count(), 20);
+
+    let n = rt.modify(|o| if o.pt[0] < 5. {
+        o.pt[1] = 10.;
+        o.tag = 2;
+    });
+    assert_eq!(n, 5);
+    assert_eq!(rt.size(), 20);
+    let moved = rt.search(&AABB::from_corners([0., 9.], [20., 11.]));
+    assert_eq!(moved.len(), 5);
+    assert!(moved.iter().all(|o| o.tag == 2));
+    assert_eq!(rt.search(&AABB::from_corners([0., -1.], [20., 1.])).len(), 15);
+
+    let n = rt.modify(|o| o.tag = 3);
+    assert_eq!(n, 0);
+    assert_eq!(rt.modify(|_| ()), 0);
+    assert_eq!(rt.iter().filter(|o| o.tag == 3).count(), 20);
+
+    let items = rt.drain();
+    assert_eq!(items.len(), 20);
+    assert!(items.iter().all(|o| o.tag == 3));
+    assert!(rt.is_empty());
+    assert_eq!(rt.iter().count(), 0);
+    rt.insert(items[0].clone());
+    assert_eq!(rt.size(), 1);
+}
+
+#[test]
+fn test_search_iter() {
+    let rt = RTree::load(init_knn());
+    let query = array_as_mbr([30, 30, 50, 50]).envelope();
+    let res = rt.search(&query);
+    assert!(!res.is_empty());
+    assert_eq!(rt.search_iter(&query).count(), res.len());
+    assert_eq!(rt.count_in_envelope(&query), res.len());
+    assert!(rt.any_in_envelope(&query));
+    for (a, b) in rt.search_iter(&query).zip(res.iter()) {
+        assert_eq!(a.as_array(), b.as_array());
+    }
+
+    let empty = array_as_mbr([200, 200, 300, 300]).envelope();
+    assert_eq!(rt.count_in_envelope(&empty), 0);
+    assert!(!rt.any_in_envelope(&empty));
+
+    let pts: Vec<[f64; 2]> = vec![[0., 0.], [1., 0.], [0., 2.], [3., 3.]];
+    let rt = RTree::load(pts);
+    let mut res = rt.within_distance_iter([0., 0.], 4.0).collect::<Vec<_>>();
+    res.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap());
+    assert_eq!(res, vec![(&[0., 0.], 0.), (&[1., 0.], 1.), (&[0., 2.], 4.)]);
+    assert_eq!(rt.locate_within_distance([0., 0.], 4.0).len(), 3);
+}
+
+#[test]
+fn test_search_containment() {
+    fn covers(a: &MBR, b: &MBR) -> bool {
+        let (a, b) = (a.as_array(), b.as_array());
+        a[0] <= b[0] && a[1] <= b[1] && a[2] >= b[2] && a[3] >= b[3]
+    }
+    let data = init_knn();
+    let rt = RTree::load(data.clone());
+    let query = array_as_mbr([30, 30, 50, 50]);
+
+    let res = rt.search_contained_in(&query.envelope());
+    assert_eq!(res.len(), 5);
+    assert_eq!(res.len(), data.iter().filter(|o| covers(&query, o)).count());
+    for o in res {
+        assert!(covers(&query, o));
+    }
+
+    let query = array_as_mbr([41, 64, 41, 64]);
+    let res = rt.search_containing(&query.envelope());
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].as_array(), array_as_mbr([41, 64, 42, 65]).as_array());
+    assert_eq!(rt.search_containing(&array_as_mbr([0, 0, 100, 100]).envelope()).len(), 0);
 }
 
+#[test]
+fn test_locate_at_point() {
+    let rt = RTree::load(init_knn());
+    let pt = array_as_mbr([41, 64, 41, 64]).envelope().lower_left();
+    let res = rt.locate_all_at_point(&pt);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].as_array(), array_as_mbr([41, 64, 42, 65]).as_array());
+    assert_eq!(rt.locate_at_point(&pt).unwrap().as_array(), res[0].as_array());
+
+    let pt = array_as_mbr([39, 65, 39, 65]).envelope().lower_left();
+    assert_eq!(rt.locate_all_at_point(&pt).len(), 1);
+    let pt = array_as_mbr([41, 65, 41, 65]).envelope().lower_left();
+    assert_eq!(rt.locate_all_at_point(&pt).len(), 2);
+    //exact geometry: keep boxes with a non-zero height
+    let res = rt.locate_all_at_point_with(&pt, |o, _| o.as_array()[1] < o.as_array()[3]);
+    assert_eq!(res.len(), 1);
+    assert_eq!(res[0].as_array(), array_as_mbr([41, 64, 42, 65]).as_array());
+    assert!(rt.locate_at_point_with(&pt, |_, _| false).is_none());
+
+    let pt = array_as_mbr([200, 200, 200, 200]).envelope().lower_left();
+    assert!(rt.locate_all_at_point(&pt).is_empty());
+    assert!(rt.locate_at_point(&pt).is_none());
+}
+
+fn env_pt(x: f64, y: f64) -> <Env as Envelope>::Point {
+    Point::generate(|i| if i == 0 { x } else { y })
+}
+
+#[test]
+fn test_search_polygon() {
+    let data = init_knn();
+    let rt = RTree::load(data.clone());
+
+    let square = vec![env_pt(30., 30.), env_pt(50., 30.), env_pt(50., 50.), env_pt(30., 50.)];
+    let res = rt.search_polygon(&square);
+    assert_eq!(res.len(), rt.search(&array_as_mbr([30, 30, 50, 50]).envelope()).len());
+
+    let triangle = vec![
+        env_pt(10., 10.), env_pt(90., 10.), env_pt(50., 90.), env_pt(10., 10.)
+    ];
+    let res = rt.search_polygon(&triangle);
+    let expects = data.iter().filter(|o| {
+        geom::envelope_polygon_relation(&o.envelope(), &triangle) != geom::Relation::Disjoint
+    }).count();
+    assert_eq!(res.len(), expects);
+    assert!(res.len() < data.len());
+    //point boxes are within or outside polygon
+    assert!(res.iter().any(|o| o.as_array() == [38., 60., 38., 60.]));
+    assert!(!res.iter().any(|o| o.as_array() == [1., 80., 1., 80.]));
+
+    let res = rt.search_polygon_with(&triangle, |_, _| false);
+    assert!(res.iter().all(|o| {
+        geom::envelope_polygon_relation(&o.envelope(), &triangle) == geom::Relation::Within
+    }));
+    assert!(rt.search_polygon(&[]).is_empty());
+}
+
+#[test]
+fn test_search_polyline_and_corridor() {
+    let data = init_knn();
+    let rt = RTree::load(data.clone());
+
+    let (a, b) = (env_pt(30., 39.), env_pt(40., 39.));
+    let res = rt.search_segment(&a, &b);
+    assert_eq!(res.len(), 2);
+    for o in res.iter() {
+        assert!(found_in(o, &knn_data_as_mbr(vec![[38, 39, 39, 39], [35, 39, 38, 40]])));
+    }
+
+    let line = vec![env_pt(0., 0.), env_pt(50., 50.), env_pt(100., 0.)];
+    let res = rt.search_polyline(&line);
+    let expects = data.iter().filter(|o| {
+        geom::polyline_segments(&line).any(|(a, b)| geom::segment_intersects_envelope(a, b, &o.envelope()))
+    }).count();
+    assert!(!res.is_empty());
+    assert_eq!(res.len(), expects);
+    assert!(rt.search_polyline_with(&line, |_, _| false).is_empty());
+
+    let res = rt.search_corridor(&line, 0.0);
+    assert_eq!(res.len(), expects);
+    let res = rt.search_corridor(&line, 5.0);
+    let expects = data.iter().filter(|o| {
+        geom::polyline_segments(&line).any(|(a, b)| geom::segment_envelope_distance(a, b, &o.envelope()) <= 5.0)
+    }).count();
+    assert!(res.len() > rt.search_polyline(&line).len());
+    assert_eq!(res.len(), expects);
+
+    //a single vertex is a zero-length segment
+    let pt = [env_pt(38.5, 39.)];
+    let res = rt.search_polyline(&pt);
+    assert!(res.iter().any(|o| found_in(o, &knn_data_as_mbr(vec![[38, 39, 39, 39]]))));
+    assert_eq!(res.len(), data.iter().filter(|o| o.envelope().contains_point(&pt[0])).count());
+    assert_eq!(rt.search_corridor(&pt, 0.0).len(), res.len());
+    let near = data.iter().filter(|o| o.envelope().distance_2(&pt[0]) <= 25.0).count();
+    assert_eq!(rt.search_corridor(&pt, 5.0).len(), near);
+}
+
+#[test]
+fn test_corridor_exact_refinement() {
+    let coords: Vec<Pt> = vec![
+        Pt { x: 0., y: 0. }, Pt { x: 10., y: 10. }, Pt { x: 20., y: 0. }, Pt { x: 30., y: 10. },
+    ];
+    let db = segment_db(&coords);
+    let line = vec![Pt { x: 10., y: 0. }, Pt { x: 10., y: 2. }];
+    let by_box = db.search_corridor(&line, 1.0);
+    let exact = db.search_corridor_with(&line, 1.0, |o, ln| {
+        seg_seg_distance(coords[o.i], coords[o.j], ln[0], ln[1]) <= 1.0
+    });
+    assert_eq!(by_box.len(), 2);
+    assert_eq!(exact.len(), 0);
+    let exact = db.search_corridor_with(&line, 6.0, |o, ln| {
+        seg_seg_distance(coords[o.i], coords[o.j], ln[0], ln[1]) <= 6.0
+    });
+    assert_eq!(exact.len(), 2);
+    assert!((distance_to_point(coords[0], coords[1], Pt { x: 10., y: 0. }) - 50f64.sqrt()).abs() < 1e-12);
+}
+
+#[test]
+fn test_raycast() {
+    let data = knn_data_as_mbr(vec![
+        [8, -1, 9, 2], [2, 0, 3, 1], [1, 5, 2, 6], [12, 0, 13, 1], [5, 0, 6, 1],
+    ]);
+    let rt = RTree::load(data);
+    let (origin, dir) = (env_pt(0., 0.5), env_pt(1., 0.));
+
+    let hits = rt.raycast(&origin, &dir, 10., |_, t| Some(t));
+    assert_eq!(hits.iter().map(|o| o.1).collect::<Vec<_>>(), vec![2., 5., 8.]);
+    assert_eq!(hits[0].0.as_array(), [2., 0., 3., 1.]);
+
+    let (first, t) = rt.raycast_first(&origin, &dir, 10., |_, t| Some(t)).unwrap();
+    assert_eq!(first.as_array(), [2., 0., 3., 1.]);
+    assert_eq!(t, 2.);
+
+    //exact geometry rejects first box, delays hit into last box
+    let hits = rt.raycast(&origin, &dir, 10., |o, t| {
+        if o.as_array()[0] == 2. { None } else if o.as_array()[0] == 8. { Some(t + 0.5) } else { Some(t) }
+    });
+    assert_eq!(hits.iter().map(|o| o.1).collect::<Vec<_>>(), vec![5., 8.5]);
+
+    assert_eq!(rt.raycast(&origin, &dir, 100., |_, t| Some(t)).len(), 4);
+    assert_eq!(rt.raycast(&origin, &dir, 1., |_, t| Some(t)).len(), 0);
+    assert!(rt.raycast_first(&env_pt(0., 0.), &env_pt(1., 1.), 10., |_, t| Some(t)).is_none());
+    assert_eq!(rt.raycast(&env_pt(0., 0.), &env_pt(1., 1.), 10., |_, t| Some(t)).len(), 0);
+}
+
+#[test]
+fn test_join() {
+    let data = init_knn();
+    let (a, b) = data.split_at(50);
+    let (ta, tb) = (RTree::load(a.to_vec()), RTree::load(b.to_vec()));
+
+    let pairs = ta.join_intersecting(&tb);
+    let expects = a.iter()
+        .flat_map(|x| b.iter().filter(move |y| x.envelope().intersects(&y.envelope())))
+        .count();
+    assert!(!pairs.is_empty());
+    assert_eq!(pairs.len(), expects);
+    for (x, y) in pairs.iter() {
+        assert!(x.envelope().intersects(&y.envelope()));
+    }
+
+    let pairs = ta.join_within_distance(&tb, 3.0);
+    let expects = a.iter()
+        .flat_map(|x| b.iter().filter(move |y| x.distance(y) <= 3.0))
+        .count();
+    assert!(pairs.len() > ta.join_intersecting(&tb).len());
+    assert_eq!(pairs.len(), expects);
+
+    let tp = RTree::load(vec![env_pt(39., 39.), env_pt(100., 100.)]);
+    let pairs = ta.join(&tp, |x, y| x.intersects(y));
+    let expects = a.iter().filter(|x| x.envelope().contains_point(&env_pt(39., 39.))).count();
+    assert_eq!(pairs.len(), expects);
+
+    //integer scalars: the squared distance of a large radius is not representable
+    let ints = RTree::load(vec![[0i64, 0], [3, 4], [100, 100]]);
+    assert_eq!(ints.join_within_distance(&ints, 5).len(), 5);
+    assert_eq!(ints.join_within_distance(&ints, std::i64::MAX).len(), 9);
+    assert_eq!(ints.self_join_within_distance(5).len(), 1);
+    assert_eq!(ints.self_join_within_distance(std::i64::MAX).len(), 3);
+}
+
+#[test]
+fn test_self_join() {
+    let data = init_knn();
+    let rt = RTree::load(data.clone());
+    let brute_force = |dist: f64| {
+        let mut count = 0;
+        for (i, a) in data.iter().enumerate() {
+            for b in data[i + 1..].iter() {
+                if a.distance(b) <= dist {
+                    count += 1;
+                }
+            }
+        }
+        count
+    };
+
+    let pairs = rt.self_join_intersecting();
+    assert_eq!(pairs.len(), brute_force(0.0));
+    for (a, b) in pairs.iter() {
+        assert!(!std::ptr::eq(*a, *b));
+        assert!(a.envelope().intersects(&b.envelope()));
+    }
+
+    let pairs = rt.self_join_within_distance(2.0);
+    assert!(pairs.len() > rt.self_join_intersecting().len());
+    assert_eq!(pairs.len(), brute_force(2.0));
+    let mut keys = pairs.iter()
+        .map(|(a, b)| {
+            let (a, b) = (*a as *const MBR as usize, *b as *const MBR as usize);
+            if a < b { (a, b) } else { (b, a) }
+        })
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys.dedup();
+    assert_eq!(keys.len(), pairs.len());
+
+    let dups = RTree::load(vec![env_pt(1., 1.), env_pt(1., 1.), env_pt(5., 5.)]);
+    assert_eq!(dups.self_join_intersecting().len(), 1);
+}
+
+#[test]
+fn test_closest_pairs() {
+    let data = init_knn();
+    let (a, b) = data.split_at(50);
+    let (ta, tb) = (RTree::load(a.to_vec()), RTree::load(b.to_vec()));
+    let dist_fn = |x: &MBR, y: &MBR| geom::envelope_distance_2(&x.envelope(), &y.envelope());
+
+    let mut expects = a.iter()
+        .flat_map(|x| b.iter().map(move |y| dist_fn(x, y)))
+        .collect::<Vec<_>>();
+    expects.sort_by(|x, y| x.partial_cmp(y).unwrap());
+
+    let pairs = ta.closest_pairs(&tb, 20, dist_fn);
+    assert_eq!(pairs.len(), 20);
+    for (i, (x, y, dist)) in pairs.iter().enumerate() {
+        assert_eq!(*dist, expects[i]);
+        assert_eq!(*dist, dist_fn(*x, *y));
+        assert!((dist.sqrt() - x.distance(y)).abs() < 1e-9);
+    }
+    assert_eq!(ta.closest_pairs(&tb, 0, dist_fn).len(), a.len() * b.len());
+    assert_eq!(ta.closest_pairs(&RTree::new(), 3, dist_fn).len(), 0);
+}
+
+#[test]
+fn test_linestring_chains_and_distance() {
+    let coords: Pts = vec![[0, 0], [1, 1], [2, 3], [3, 2], [4, 0], [4, -1], [5, 1], [6, 1]].into();
+    let chains = linestring::monotone_chains(&coords.pts);
+    let ranges = chains.iter().map(|o| (o.i, o.j)).collect::<Vec<_>>();
+    assert_eq!(ranges, vec![(0, 2), (2, 5), (5, 7)]);
+    assert_eq!(chains[1], MonoMBR::new(Pt { x: 2., y: -1. }, Pt { x: 4., y: 3. }, 2, 5));
+    assert_eq!(linestring::segment_bounds(&coords.pts).len(), 7);
+    assert_eq!(linestring::chain_db(&coords.pts).size(), 3);
+
+    let other: Pts = vec![[0., 5.], [3., 4.5], [7., 5.]].into();
+    assert_eq!(linestring::min_distance(&coords.pts, &other.pts), min_dist_brute_force(&other.pts, &coords.pts));
+    let dist = linestring::min_distance(&coords.pts, &[Pt { x: 4., y: 4. }]);
+    assert!((dist - 4.5f64.sqrt()).abs() < 1e-12);
+    assert_eq!(linestring::min_distance(&coords.pts, &coords.pts), 0.0);
+    assert!(linestring::min_distance(&coords.pts, &[]).is_infinite());
+}
+
+#[test]
+fn test_mono_chain_index() {
+    let coords: Pts = vec![[0, 0], [1, 1], [2, 3], [3, 2], [4, 0], [4, -1], [5, 1], [6, 1]].into();
+    let index = linestring::MonoChainIndex::new(coords.pts.clone());
+    assert_eq!(index.chains().size(), 3);
+    assert_eq!(index.coords().len(), 8);
+
+    let (a, b) = (Pt { x: 0., y: 1. }, Pt { x: 6., y: 1. });
+    assert_eq!(index.intersecting_segments(&a, &b), vec![(0, 1), (1, 2), (3, 4), (5, 6), (6, 7)]);
+    let (a, b) = (Pt { x: 0., y: 4. }, Pt { x: 6., y: 4. });
+    assert!(index.intersecting_segments(&a, &b).is_empty());
+
+    let (k, dist) = index.nearest_vertex(&Pt { x: 4.2, y: -0.4 }).unwrap();
+    assert_eq!(k, 4);
+    assert!((dist - 0.2f64.sqrt()).abs() < 1e-12);
+    assert_eq!(index.nearest_vertex(&Pt { x: 7., y: 2. }).map(|o| o.0), Some(7));
+    assert!(linestring::MonoChainIndex::<Pt>::new(vec![]).nearest_vertex(&a).is_none());
+}
+
+#[test]
+fn test_hausdorff_and_frechet() {
+    let a: Pts = vec![[0, 0], [10, 0]].into();
+    let b: Pts = vec![[0, 1], [5, 3], [10, 1]].into();
+    assert_eq!(linestring::hausdorff_distance(&a.pts, &b.pts), 3.0);
+    assert_eq!(linestring::hausdorff_distance(&b.pts, &a.pts), 3.0);
+    assert_eq!(linestring::frechet_distance(&a.pts, &b.pts), 34f64.sqrt());
+    assert_eq!(linestring::frechet_distance(&b.pts, &a.pts), 34f64.sqrt());
+
+    let c: Pts = vec![[0, 1], [1, 1], [2, 1]].into();
+    let d: Pts = vec![[0, 0], [1, 0], [2, 0]].into();
+    assert_eq!(linestring::hausdorff_distance(&c.pts, &d.pts), 1.0);
+    assert_eq!(linestring::frechet_distance(&c.pts, &d.pts), 1.0);
+    assert_eq!(linestring::hausdorff_distance(&c.pts, &c.pts), 0.0);
+    assert_eq!(linestring::frechet_distance(&c.pts, &c.pts), 0.0);
+    assert!(linestring::hausdorff_distance(&c.pts, &[]).is_infinite());
+    assert!(linestring::frechet_distance(&[], &c.pts).is_infinite());
+}
+
+#[test]
+fn test_farthest() {
+    let data = init_knn();
+    let rt = RTree::load(data.clone());
+    let q = env_pt(40., 40.);
+    let far = |o: &MBR| {
+        let e = o.envelope();
+        let (ll, ur) = (e.lower_left(), e.upper_right());
+        (0..2).map(|i| {
+            let d = (q.nth(i) - ll.nth(i)).max(ur.nth(i) - q.nth(i));
+            d * d
+        }).sum::<f64>()
+    };
+    let mut expects = data.iter().map(far).collect::<Vec<_>>();
+    expects.sort_by(|a, b| b.partial_cmp(a).unwrap());
+
+    let res = rt.farthest_from_point(&q, 10);
+    assert_eq!(res.len(), 10);
+    for (i, (item, dist)) in res.iter().enumerate() {
+        assert_eq!(*dist, expects[i]);
+        assert_eq!(*dist, far(*item));
+    }
+    let all = rt.farthest_from_point(&q, 0);
+    assert_eq!(all.len(), data.len());
+    assert!(all.windows(2).all(|w| w[0].1 >= w[1].1));
+
+    //farthest item center, parent nodes are bounded by their farthest corner
+    let center = |o: &MBR| {
+        let e = o.envelope();
+        let (ll, ur) = (e.lower_left(), e.upper_right());
+        env_pt((ll.nth(0) + ur.nth(0)) / 2., (ll.nth(1) + ur.nth(1)) / 2.)
+    };
+    let (item, _) = rt.farthest(&q, 1, |q, item, o| match item {
+        Some(item) => {
+            let c = center(item);
+            (c.nth(0) - q.nth(0)).powi(2) + (c.nth(1) - q.nth(1)).powi(2)
+        }
+        None => geom::envelope_max_distance_2(&o.mbr, q),
+    })[0];
+    let expects = data.iter().map(|o| {
+        let c = center(o);
+        (c.nth(0) - q.nth(0)).powi(2) + (c.nth(1) - q.nth(1)).powi(2)
+    }).fold(0.0, f64::max);
+    let c = center(item);
+    assert_eq!((c.nth(0) - q.nth(0)).powi(2) + (c.nth(1) - q.nth(1)).powi(2), expects);
+
+    //NaN scores are yielded after every scored item
+    let is_nan_item = |o: &MBR| center(o).nth(0) < 20.;
+    let res = rt.farthest(&q, 0, |q, item, o| match item {
+        Some(item) if is_nan_item(item) => std::f64::NAN,
+        _ => geom::envelope_max_distance_2(&o.mbr, q),
+    });
+    assert_eq!(res.len(), data.len());
+    let nans = data.iter().filter(|o| is_nan_item(*o)).count();
+    let (scored, tail) = res.split_at(data.len() - nans);
+    assert!(scored.iter().all(|(_, d)| !d.is_nan()));
+    assert!(scored.windows(2).all(|w| w[0].1 >= w[1].1));
+    assert!(tail.iter().all(|(item, d)| d.is_nan() && is_nan_item(*item)));
+    assert!(RTree::<MBR>::new().farthest_from_point(&q, 3).is_empty());
+}
 
 #[test]
 fn knn_min_dist() {
@@ -164,11 +878,11 @@ fn knn_min_dist() {
     let ln_a = apts.pts;
     let ln_b = bpts.pts;
     let expects = min_dist_brute_force(&ln_a, &ln_b);
-    let actual = knn_min_linear_distance(&ln_a, &ln_b);
+    let actual = linestring::min_distance(&ln_a, &ln_b);
     assert_eq!(expects, actual);
     assert_eq!(
-        knn_min_linear_distance(&ln_a, &ln_b),
-        knn_min_linear_distance(&ln_b, &ln_a)
+        linestring::min_distance(&ln_a, &ln_b),
+        linestring::min_distance(&ln_b, &ln_a)
     );
 
     //"LINESTRING ( 107.40136085597902 264.4275018068343, 104.75816310001828 261.7843040508735, 100.499677826526 257.52581877738123, 95.06643799482896 252.0925789456842, 101.23389942540399 247.39356071286514, 105.27467042529447 243.22244226136527, 110.33824725149094 237.99552424722697, 107.97360483400409 233.15363739237296, 104.17078582091591 225.3669127465257, 107.33973515096297 223.0397155822724, 112.55672605179302 219.20848788947535, 114.73295654185358 217.61031862333712, 118.38485017243461 214.9284592383792, 122.96685875219221 211.5635466876197, 129.01219223364694 213.86653087103102, 135.30178161334229 216.26256492043876, 135.48601339593614 223.90818389808322, 135.68422020736688 232.13376657245925, 135.8042861563376 237.11650345474465, 135.88915889244467 240.6387220031877, 142.18950024983 242.6188292869374, 146.1682612767364 243.86929703825083, 150.0675155613293 244.623991415914, 155.27260910282334 245.631428875558, 154.86258122926995 253.5244654414607, 154.49661900188642 260.5692383185941, 154.32924858795883 263.7911187867, 154.09785454461857 268.2454541209998, 150.84751029246013 272.4058947637626, 148.34073794456262 275.61456336907145, 146.75563855583877 277.64349058663794 )"
@@ -176,11 +890,11 @@ fn knn_min_dist() {
     let a: Pts = vec![[107.401360856, 264.427501807], [104.7581631, 261.784304051], [100.499677827, 257.525818777], [95.0664379948, 252.092578946], [101.233899425, 247.393560713], [105.274670425, 243.222442261], [110.338247251, 237.995524247], [107.973604834, 233.153637392], [104.170785821, 225.366912747], [107.339735151, 223.039715582], [112.556726052, 219.208487889], [114.732956542, 217.610318623], [118.384850172, 214.928459238], [122.966858752, 211.563546688], [129.012192234, 213.866530871], [135.301781613, 216.26256492], [135.486013396, 223.908183898], [135.684220207, 232.133766572], [135.804286156, 237.116503455], [135.889158892, 240.638722003], [142.18950025, 242.618829287], [146.168261277, 243.869297038], [150.067515561, 244.623991416], [155.272609103, 245.631428876], [154.862581229, 253.524465441], [154.496619002, 260.569238319], [154.329248588, 263.791118787], [154.097854545, 268.245454121], [150.847510292, 272.405894764], [148.340737945, 275.614563369], [146.755638556, 277.643490587]].into();
     let b: Pts = vec![[52.1878966204, 219.493139956], [53.2609636091, 225.778246604], [54.2437170972, 231.534374177], [58.0571674214, 234.890210462], [61.585933086, 237.995524247], [69.5155263539, 233.296506014], [71.5713468307, 226.835355944], [68.6344604352, 218.318385397], [75.6209195668, 215.631285731], [80.0883173777, 213.913055804], [82.8303409251, 217.966481918], [86.8431560874, 223.898469549], [86.0811531307, 228.470487289], [85.0810242501, 234.471260573], [81.4392851197, 241.754738833], [78.3261855404, 247.980937992], [80.7344323847, 255.205678525], [82.4378264941, 260.315860853], [88.3338416844, 269.946018997], [91.2484856807, 274.706604191], [85.2471146393, 277.354267886], [76.9247314241, 281.02590754], [71.2776581912, 283.517263378], [63.3868418399, 278.157463592], [60.2927014542, 276.05578333], [55.712160295, 272.944472354], [52.5128894544, 276.564699884], [49.896536606, 279.525309686], [47.1683448405, 282.612474052], [44.551991992, 285.573083855], [44.2583033525, 291.446856646], [48.6636329457, 295.852186239], [52.4815852599, 299.670138553], [58.676489198, 299.074474713], [67.7533945166, 298.201695355], [73.437123131, 295.958118271], [78.9135628195, 293.796365762], [80.794310294, 290.751346041], [85.0810242501, 283.810952017], [90.5706205505, 285.500058571], [100.352833507, 288.50997025], [105.694304117, 287.697137766], [113.862510926, 286.454149773], [115.303247648, 281.411571245], [116.876975453, 275.90352393], [118.428538077, 270.473054746], [119.736283717, 265.895945005], [120.911038275, 261.784304051]].into();
     let expects = min_dist_brute_force(&a.pts, &b.pts);
-    let actual = knn_min_linear_distance(&a.pts, &b.pts);
+    let actual = linestring::min_distance(&a.pts, &b.pts);
     assert_eq!(expects, actual);
     assert_eq!(
-        knn_min_linear_distance(&a.pts, &b.pts),
-        knn_min_linear_distance(&b.pts, &a.pts),
+        linestring::min_distance(&a.pts, &b.pts),
+        linestring::min_distance(&b.pts, &a.pts),
     );
 
     //"LINESTRING ( 190.5152458489664 281.16775426125224, 182.87934122063538 276.175047388882, 188.75311401165925 269.42020867920456, 193.45213224447832 269.12652003965337, 193.7458208840295 263.8401245277319, 188.16573673255687 259.1411062949128, 183.17302986018657 261.7843040508735, 177.88663434826512 255.9105312598497, 184.93516169749373 248.8620039106211, 193.7458208840295 252.0925789456842 )";
@@ -188,7 +902,22 @@ fn knn_min_dist() {
     let a: Pts = vec![[190.515245849, 281.167754261], [182.879341221, 276.175047389], [188.753114012, 269.420208679], [193.452132244, 269.12652004], [193.745820884, 263.840124528], [188.165736733, 259.141106295], [183.17302986, 261.784304051], [177.886634348, 255.91053126], [184.935161697, 248.862003911], [193.745820884, 252.092578946]].into();
     let b: Pts = vec![[184.054095779, 283.223574738], [176.418191151, 278.818245145], [176.418191151, 273.238160993], [180.529832104, 270.594963237], [181.998275302, 268.245454121], [180.823520744, 265.602256365], [175.243436592, 264.133813167], [171.131795639, 260.022172214], [172.306550197, 255.91053126], [176.71187979, 250.330447108], [179.942454825, 243.869297038], [188.753114012, 242.107165201]].into();
     let expects = min_dist_brute_force(&a.pts, &b.pts);
-    let actual = knn_min_linear_distance(&a.pts, &b.pts);
+    let actual = linestring::min_distance(&a.pts, &b.pts);
     assert_eq!(expects, actual);
-    assert_eq!(knn_min_linear_distance(&a.pts, &b.pts), knn_min_linear_distance(&b.pts, &a.pts));
-}
\ No newline at end of file
+    assert_eq!(linestring::min_distance(&a.pts, &b.pts), linestring::min_distance(&b.pts, &a.pts));
+
+    let (db_a, db_b) = (segment_db(&a.pts), segment_db(&b.pts));
+    let pairs = db_a.closest_pairs(&db_b, 1, |x: &MonoMBR, y: &MonoMBR| {
+        seg_seg_distance(a.pts[x.i], a.pts[x.j], b.pts[y.i], b.pts[y.j]).powi(2)
+    });
+    assert_eq!(pairs[0].2, expects.powi(2));
+
+    let directed = |p: &Vec<Pt>, q: &Vec<Pt>| p.iter().map(|v| {
+        q.windows(2).map(|w| seg_seg_distance(*v, *v, w[0], w[1])).fold(std::f64::INFINITY, f64::min)
+    }).fold(0.0, f64::max);
+    let expects = directed(&a.pts, &b.pts).max(directed(&b.pts, &a.pts));
+    assert_eq!(linestring::hausdorff_distance(&a.pts, &b.pts), expects);
+
+    assert_eq!(linestring::frechet_distance(&a.pts, &b.pts), frechet_brute_force(&a.pts, &b.pts));
+    assert_eq!(linestring::frechet_distance(&b.pts, &a.pts), frechet_brute_force(&b.pts, &a.pts));
+}
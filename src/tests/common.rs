@@ -1,8 +1,7 @@
 use coordinate::Coordinate;
-use math_util::{num, NumCast, Feq, EPSILON};
-use rstar::{AABB, RTreeObject, PointDistance};
-use crate::kobj::KObj;
-use crate::RTree;
+use math_util::{num, NumCast, Feq};
+use rstar::RTreeObject;
+use crate::{linestring, seg_seg_distance};
 
 #[derive(Copy, Clone, PartialOrd, Debug)]
 pub struct Pt {
@@ -68,85 +67,25 @@ impl rstar::Point for Pt {
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct MonoMBR {
-    ll: Pt,
-    ur: Pt,
-    i: usize,
-    j: usize,
-}
-
-impl MonoMBR {
-    pub fn new(a: Pt, b: Pt, i: usize, j: usize) -> MonoMBR {
-        MonoMBR {
-            ll: a.min_of_bounds(&b),
-            ur: a.max_of_bounds(&b),
-            i,
-            j,
-        }
-    }
-
-    pub fn equals(&self, other: &Self) -> bool {
-        self.ll.equals(&other.ll) & &self.ur.equals(&other.ur)
-    }
-
-    pub fn intersects(&self, other: &Self) -> bool {
-        //not disjoint
-        !(other.ll.x > self.ur.x
-            || other.ur.x < self.ll.x
-            || other.ll.y > self.ur.y
-            || other.ur.y < self.ll.y)
-    }
-    pub fn distance_dxdy(&self, other: &Self) -> (f64, f64) {
-        let mut dx = 0.0;
-        let mut dy = 0.0;
-
-        // find closest edge by x
-        if self.ur.x < other.ll.x {
-            dx = other.ll.x - self.ur.x
-        } else if self.ll.x > other.ur.x {
-            dx = self.ll.x - other.ur.x
-        }
+pub type MonoMBR = linestring::MonoMBR<Pt>;
 
-        // find closest edge by y
-        if self.ur.y < other.ll.y {
-            dy = other.ll.y - self.ur.y
-        } else if self.ll.y > other.ur.y {
-            dy = self.ll.y - other.ur.y
-        }
+pub trait Wkt {
+    fn wkt(&self) -> String;
+}
 
-        (dx, dy)
-    }
-    pub fn distance_square(&self, other: &Self) -> f64 {
-        if self.intersects(other) {
-            return 0.0;
-        }
-        let (dx, dy) = self.distance_dxdy(other);
-        (dx * dx) + (dy * dy)
-    }
-    pub fn wkt(&self) -> String {
+impl Wkt for MonoMBR {
+    fn wkt(&self) -> String {
+        let (ll, ur) = (self.envelope().lower(), self.envelope().upper());
         format!(
             "POLYGON (({lx} {ly},{lx} {uy},{ux} {uy},{ux} {ly},{lx} {ly}))",
-            lx = self.ll.x,
-            ly = self.ll.y,
-            ux = self.ur.x,
-            uy = self.ur.y
+            lx = ll.x,
+            ly = ll.y,
+            ux = ur.x,
+            uy = ur.y
         )
     }
 }
 
-impl PartialEq for MonoMBR {
-    fn eq(&self, other: &Self) -> bool {
-        self.equals(other)
-    }
-}
-
-impl From<AABB<Pt>> for MonoMBR {
-    fn from(aab: AABB<Pt>) -> Self {
-        MonoMBR::new(aab.lower(), aab.upper(), 0, 0)
-    }
-}
-
 impl<T> From<[T; 2]> for Pt where T: NumCast + Copy {
     fn from(array: [T; 2]) -> Self {
         Pt { x: num::cast(array[0]).unwrap(), y: num::cast(array[1]).unwrap() }
@@ -170,19 +109,6 @@ impl<T> From<Vec<[T; 2]>> for Pts where T: NumCast + Copy {
 }
 
 
-impl RTreeObject for MonoMBR {
-    type Envelope = AABB<Pt>;
-    fn envelope(&self) -> Self::Envelope {
-        AABB::from_corners(self.ll, self.ur)
-    }
-}
-
-impl PointDistance for MonoMBR {
-    fn distance_2(&self, pt: &Pt) -> f64 {
-        self.distance_square(&MonoMBR::new(*pt, *pt, 0, 0))
-    }
-}
-
 // brute force distance
 pub fn min_dist_brute_force(ln: &Vec<Pt>, ln2: &Vec<Pt>) -> f64 {
     let mut dist = std::f64::MAX;
@@ -205,170 +131,27 @@ pub fn min_dist_brute_force(ln: &Vec<Pt>, ln2: &Vec<Pt>) -> f64 {
     dist
 }
 
-
-pub fn knn_min_linear_distance(a_coords: &Vec<Pt>, b_coords: &Vec<Pt>) -> f64 {
-    let ( a, b) = if a_coords.len() > b_coords.len() {
-        (b_coords, a_coords)
-    } else {
-        (a_coords, b_coords)
+// brute force discrete frechet distance
+pub fn frechet_brute_force(ln: &Vec<Pt>, ln2: &Vec<Pt>) -> f64 {
+    let dist = |a: &Pt, b: &Pt| {
+        let (dx, dy) = (a.x - b.x, a.y - b.y);
+        (dx * dx + dy * dy).sqrt()
     };
-
-    let db = segment_db(b);
-    let queries = query_bounds(a);
-
-    let mut min_dist = std::f64::MAX;
-    let dist_fn = |query: &MonoMBR, item: &MonoMBR| {
-        seg_seg_distance(a[query.i], a[query.j], b[item.i], b[item.j])
-    };
-    let pred_fn = |o: KObj, dist: f64| {
-        o.distance > dist || dist == 0f64 //add to neibs, stop
-    };
-    for q in queries.iter() {
-        min_dist = db.knn_min_dist(q, dist_fn, pred_fn, min_dist)
-    }
-
-    return min_dist;
-}
-
-
-pub fn segment_db(coords: &Vec<Pt>) -> RTree<MonoMBR> {
-    let n = coords.len() - 1;
-    let mut items = Vec::with_capacity(n);
-    for i in 0..n {
-        let j = i + 1;
-        items.push(MonoMBR::new(coords[i], coords[j], i, j));
-    }
-    RTree::load(items)
-}
-
-fn query_bounds(coords: &Vec<Pt>) -> Vec<MonoMBR> {
-    let n = coords.len() - 1;
-    let mut items = Vec::with_capacity(n);
-    for i in 0..n {
-        let j = i + 1;
-        items.push(MonoMBR::new(coords[i], coords[j], i, j))
-    }
-    return items;
-}
-
-
-//Distance between two segments with custom hypot function
-pub fn seg_seg_distance(sa: Pt, sb: Pt, oa: Pt, ob: Pt) -> f64 {
-    let  dist;
-    let (x1, y1) = (sa.x, sa.y);
-    let (x2, y2) = (sb.x, sb.y);
-
-    let (x3, y3) = (oa.x, oa.y);
-    let (x4, y4) = (ob.x, ob.y);
-
-
-    let denom = (y4 - y3) * (x2 - x1) - (x4 - x3) * (y2 - y1);
-    let numera = (x4 - x3) * (y1 - y3) - (y4 - y3) * (x1 - x3);
-    let numerb = (x2 - x1) * (y1 - y3) - (y2 - y1) * (x1 - x3);
-
-    let mut pta = sa;
-    let mut ptb = sb;
-    if (denom.abs()) < EPSILON {
-        let is_aspt_a = sa.equals(&sb);
-        let is_aspt_b = oa.equals(&ob);
-
-        if is_aspt_a && is_aspt_b {
-            dist = (x1 - x4).hypot(y1 - y4)
-        } else if is_aspt_a || is_aspt_b {
-            let (lna, lnb) = if is_aspt_a {
-                pta = sa;
-                (oa, ob)
-            } else if is_aspt_b {
-                pta = oa;
-                (sa, sb)
+    let (n, m) = (ln.len(), ln2.len());
+    let mut ca = vec![vec![std::f64::INFINITY; m]; n];
+    for (i, u) in ln.iter().enumerate() {
+        for (j, v) in ln2.iter().enumerate() {
+            let d = dist(u, v);
+            ca[i][j] = if i == 0 && j == 0 {
+                d
+            } else if i == 0 {
+                ca[0][j - 1].max(d)
+            } else if j == 0 {
+                ca[i - 1][0].max(d)
             } else {
-                unreachable!()
+                ca[i - 1][j].min(ca[i - 1][j - 1]).min(ca[i][j - 1]).max(d)
             };
-
-            dist = distance_to_point(lna, lnb, pta)
-        } else {
-            dist = min_dist_segment_endpoints(sa, sb, oa, ob)
-        }
-    } else {
-        let (mut use_pta, mut use_ptb) = (false, false);
-        let mua = numera / denom;
-        let mua = snap_to_zero_or_one(mua);
-
-        let mub = numerb / denom;
-        let mub = snap_to_zero_or_one(mub);
-
-        if mua < 0f64 || mua > 1f64 || mub < 0f64 || mub > 1f64 {
-            //the is intersection along the the segments
-            if mua < 0f64 {
-                pta = sa;
-                use_pta = true;
-            } else if mua > 1f64 {
-                pta = sb;
-                use_pta = true;
-            }
-
-            if mub < 0f64 {
-                ptb = oa;
-                use_ptb = true;
-            } else if mub > 1f64 {
-                ptb = ob;
-                use_ptb = true;
-            }
-
-            if use_pta && use_ptb {
-                dist = f64::min(
-                    distance_to_point(oa, ob, pta),
-                    distance_to_point(sa, sb, ptb),
-                )
-            } else if use_pta {
-                dist = distance_to_point(oa, ob, pta)
-            } else {
-                dist = distance_to_point(sa, sb, ptb)
-            }
-        } else {
-            dist = 0f64; //lines intersect
         }
     }
-    dist
-}
-
-fn min_dist_segment_endpoints(sa: Pt, sb: Pt, oa: Pt, ob: Pt) -> f64 {
-    let o_sa = distance_to_point(oa, ob, sa);
-    let o_sb = distance_to_point(oa, ob, sb);
-    let s_oa = distance_to_point(sa, sb, oa);
-    let s_ob = distance_to_point(sa, sb, ob);
-    (o_sa.min(o_sb)).min(s_oa.min(s_ob))
-}
-
-//Distance from segment endpoints to point
-fn distance_to_point(sa: Pt, sb: Pt, pt: Pt) -> f64 {
-    let (ax, ay) = (sa.x, sa.y);
-    let (bx, by) = (sb.x, sb.y);
-    let (px, py) = (pt.x, pt.y);
-    let (dx, dy) = (bx - ax, by - ay);
-    let isz_x = dx.feq(0f64);
-    let isz_y = dy.feq(0f64);
-
-    if isz_x && isz_y {
-        //line with zero length
-        (px - ax).hypot(py - ay)
-    } else {
-        let u = (((px - ax) * dx) + ((py - ay) * dy)) / (dx * dx + dy * dy);
-
-        let (c_ptx, c_pty) = if u < 0.0 {
-            (ax, ay)
-        } else if u > 1f64 {
-            (bx, by)
-        } else {
-            (ax + u * dx, ay + u * dy)
-        };
-        (px - c_ptx).hypot(py - c_pty)
-    }
-}
-
-#[inline]
-///Snap value to zero or one
-pub fn snap_to_zero_or_one(x: f64) -> f64 {
-    if x.feq(0.0) { 0.0 } else if x.feq(1.0) { 1.0 } else { x }
+    ca[n - 1][m - 1]
 }
-